@@ -0,0 +1,104 @@
+use crate::json::{Array, Key, Object, Value};
+
+/// Builds an object `Value` member by member.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectBuilder {
+    object: Object,
+}
+
+impl ObjectBuilder {
+    pub fn new() -> ObjectBuilder {
+        ObjectBuilder::default()
+    }
+
+    /// Sets `key` to `value`, replacing any earlier value for the key.
+    pub fn set<K: Into<Key>, V: Into<Value>>(mut self, key: K, value: V) -> ObjectBuilder {
+        self.object.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets `key` only when `value` is `Some`.
+    pub fn set_opt<K: Into<Key>, V: Into<Value>>(self, key: K, value: Option<V>) -> ObjectBuilder {
+        match value {
+            Some(v) => self.set(key, v),
+            None => self,
+        }
+    }
+
+    pub fn build(self) -> Value {
+        Value::Object(self.object)
+    }
+}
+
+/// Builds an array `Value` element by element.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArrayBuilder {
+    array: Array,
+}
+
+impl ArrayBuilder {
+    pub fn new() -> ArrayBuilder {
+        ArrayBuilder::default()
+    }
+
+    pub fn push<V: Into<Value>>(mut self, value: V) -> ArrayBuilder {
+        self.array.push(value.into());
+        self
+    }
+
+    pub fn build(self) -> Value {
+        Value::Array(self.array)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::{ArrayBuilder, ObjectBuilder};
+
+    #[test]
+    fn object() {
+        let verbose = false;
+        let mut server = ObjectBuilder::new()
+            .set("host", "localhost")
+            .set("port", 8080);
+        if verbose {
+            server = server.set("log", "debug");
+        }
+
+        let v = ObjectBuilder::new()
+            .set("a", 1)
+            .set("b", json!([1, 2]))
+            .set("server", server.build())
+            .set_opt("alias", None::<&str>)
+            .set_opt("name", Some("rson"))
+            .build();
+
+        assert_eq!(
+            v,
+            json!({
+                "a": 1,
+                "b": [1, 2],
+                "server": { "host": "localhost", "port": 8080 },
+                "name": "rson"
+            })
+        );
+        assert_eq!(
+            ObjectBuilder::new().set("a", 1).set("a", 2).build(),
+            json!({ "a": 2 })
+        );
+        assert_eq!(ObjectBuilder::new().build(), json!({}));
+    }
+
+    #[test]
+    fn array() {
+        let v = ArrayBuilder::new()
+            .push(true)
+            .push("x")
+            .push(ArrayBuilder::new().push(1.5).build())
+            .push(ObjectBuilder::new().set("k", json!(null)).build())
+            .build();
+
+        assert_eq!(v, json!([true, "x", [1.5], { "k": null }]));
+        assert_eq!(ArrayBuilder::new().build(), json!([]));
+    }
+}
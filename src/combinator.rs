@@ -1,20 +1,248 @@
-use std::collections::HashMap;
-
-use std::fmt;
-use std::fmt::Display;
-use std::fmt::Formatter;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+use core::fmt::Display;
+use core::fmt::Formatter;
 
+/// Why parsing failed. Variants locate the problem by byte offset into the
+/// input, except those about the input as a whole. More variants may be added,
+/// so matches need a wildcard arm.
 #[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum Error {
-    FoundUnTerminatedError,
-    ParseError,
+    /// Non-whitespace input follows the root value, starting at the given byte offset.
+    FoundUnTerminatedError(usize),
+    /// Parsing failed at the given byte offset into the input.
+    ParseError(usize),
+    /// The input is longer than `ParseOptions::max_total_bytes`.
+    InputTooLarge,
+    /// Arrays and objects are nested deeper than `ParseOptions::max_depth`
+    /// allows; the offset is that of the first container over the limit.
+    DepthLimitExceeded(usize),
+    /// A string is longer than `ParseOptions::max_string_len`; the offset is that
+    /// of its opening quote.
+    StringTooLong(usize),
+    /// Bytes that are neither UTF-8 nor UTF-16 with a byte order mark.
+    InvalidEncoding,
+    /// Input that breaks strict JSON at the given byte offset, with the reason.
+    StrictViolation(usize, &'static str),
+    /// The input ends inside an array or object; the offset is that of its
+    /// opening bracket or brace.
+    UnclosedContainer(usize),
+    /// An object key is not followed by `:`; the offset is where it was expected.
+    MissingColon(usize),
+    /// A `:` appears between the elements of an array.
+    UnexpectedColon(usize),
+    /// The root is not the kind `ParseOptions::require_root` asks for; the offset
+    /// is that of its opening bracket.
+    UnexpectedRootKind(usize),
+    /// A byte order mark after the start of the document. A leading one is only
+    /// skipped by `parse_detect_encoding`.
+    UnexpectedBom(usize),
+}
+
+impl Error {
+    /// Byte offset into the input where the error was detected, if known.
+    /// Use [`char_index`] to turn it into a character index.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Error::ParseError(p)
+            | Error::FoundUnTerminatedError(p)
+            | Error::DepthLimitExceeded(p)
+            | Error::StringTooLong(p)
+            | Error::StrictViolation(p, _)
+            | Error::UnclosedContainer(p)
+            | Error::MissingColon(p)
+            | Error::UnexpectedColon(p)
+            | Error::UnexpectedRootKind(p)
+            | Error::UnexpectedBom(p) => Some(*p),
+            _ => None,
+        }
+    }
+
+    /// Formats the error for `input` with a line and column instead of a byte offset.
+    pub fn report<'a>(&'a self, input: &'a str) -> Report<'a> {
+        Report {
+            error: self,
+            input,
+            file: None,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Error::FoundUnTerminatedError(_) => "found unterminated json".to_string(),
+            Error::ParseError(_) => "failed to parse value".to_string(),
+            Error::DepthLimitExceeded(_) => "nesting is too deep".to_string(),
+            Error::StringTooLong(_) => "string is too long".to_string(),
+            Error::StrictViolation(_, reason) => format!("found {}", reason),
+            Error::UnclosedContainer(_) => "container is never closed".to_string(),
+            Error::MissingColon(_) => "expected `:` after key".to_string(),
+            Error::UnexpectedColon(_) => "found `:` in array".to_string(),
+            Error::UnexpectedRootKind(_) => "unexpected kind of root".to_string(),
+            Error::UnexpectedBom(_) => "found byte order mark".to_string(),
+            e => {
+                let mut m = e.to_string();
+                m[..1].make_ascii_lowercase();
+                m
+            }
+        }
+    }
+}
+
+/// `Display` of an `Error` located in its input, as `file:line:col: message`, or
+/// `error at line L, column C: message` when no file name is set. Lines and
+/// columns count from 1, columns in characters.
+pub struct Report<'a> {
+    error: &'a Error,
+    input: &'a str,
+    file: Option<&'a str>,
+}
+
+impl<'a> Report<'a> {
+    pub fn file(mut self, name: &'a str) -> Report<'a> {
+        self.file = Some(name);
+        self
+    }
+}
+
+impl<'a> Display for Report<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let message = self.error.message();
+        match (self.error.position(), self.file) {
+            (Some(p), Some(file)) => {
+                let (line, column) = line_column(self.input, p);
+                write!(f, "{}:{}:{}: {}", file, line, column, message)
+            }
+            (Some(p), None) => {
+                let (line, column) = line_column(self.input, p);
+                write!(f, "error at line {}, column {}: {}.", line, column, message)
+            }
+            (None, Some(file)) => write!(f, "{}: {}", file, message),
+            (None, None) => write!(f, "error: {}.", message),
+        }
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            Error::FoundUnTerminatedError => write!(f, "Found unterminated json"),
-            Error::ParseError => write!(f, "Failed to parse value"),
+            Error::FoundUnTerminatedError(p) => {
+                write!(f, "Found unterminated json at byte {}", p)
+            }
+            Error::ParseError(p) => write!(f, "Failed to parse value at byte {}", p),
+            Error::InputTooLarge => write!(f, "Input is too large"),
+            Error::DepthLimitExceeded(p) => write!(f, "Nesting is too deep at byte {}", p),
+            Error::StringTooLong(p) => write!(f, "String is too long at byte {}", p),
+            Error::InvalidEncoding => write!(f, "Input is not UTF-8 or UTF-16"),
+            Error::StrictViolation(p, reason) => write!(f, "Found {} at byte {}", reason, p),
+            Error::UnclosedContainer(p) => write!(f, "Container at byte {} is never closed", p),
+            Error::MissingColon(p) => write!(f, "Expected `:` after key at byte {}", p),
+            Error::UnexpectedColon(p) => write!(f, "Found `:` in array at byte {}", p),
+            Error::UnexpectedRootKind(p) => write!(f, "Unexpected kind of root at byte {}", p),
+            Error::UnexpectedBom(p) => write!(f, "Found byte order mark at byte {}", p),
+        }
+    }
+}
+
+/// Converts a byte offset into `s` to the index of the character containing it.
+pub fn char_index(s: &str, byte_offset: usize) -> usize {
+    s.char_indices()
+        .take_while(|(i, c)| i + c.len_utf8() <= byte_offset)
+        .count()
+}
+
+/// 1-based line and column of the character containing `byte_offset` in `s`.
+pub fn line_column(s: &str, byte_offset: usize) -> (usize, usize) {
+    let mut end = byte_offset.min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    let before = &s[..end];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    (line, char_index(&s[line_start..], end - line_start) + 1)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Upper bound on the input length in bytes, checked before parsing starts.
+    pub max_total_bytes: Option<usize>,
+    /// Rejects syntax that JSON forbids but is tolerated by default: a leading
+    /// `+` on numbers, leading zeros such as `01`, and unescaped control
+    /// characters in strings. It also disables `undefined_as_null`.
+    pub strict: bool,
+    /// Allows `// line` and `/* block */` comments wherever whitespace may appear.
+    pub comments: bool,
+    /// Upper bound on the nesting of arrays and objects, the root container being
+    /// at depth 1. Without it, deeply nested input can overflow the stack.
+    pub max_depth: Option<usize>,
+    /// Upper bound on the decoded length in bytes of each string and key.
+    pub max_string_len: Option<usize>,
+    /// Allows a comma after the last element of an array or object.
+    pub trailing_commas: bool,
+    /// Allows strings and keys in single quotes, in which `\'` escapes a quote.
+    pub single_quotes: bool,
+    /// Allows object keys written as bare identifiers, such as `{name: 1}`.
+    pub unquoted_keys: bool,
+    /// Allows `\xNN` escapes in strings, decoded to the code point `U+00NN`.
+    pub hex_escapes: bool,
+    /// What to do with a key that appears more than once in an object.
+    pub duplicate_keys: DuplicateKeys,
+    /// Rejects keys containing U+0000, which JSON allows but many consumers
+    /// mishandle, with `Error::StrictViolation`.
+    pub reject_nul_in_keys: bool,
+    /// The kind of root container to accept.
+    pub require_root: RootKind,
+    /// Reads the JavaScript literal `undefined` as `null`. This is lossy: the
+    /// value serializes back as `null`. Ignored when `strict` is set.
+    pub undefined_as_null: bool,
+}
+
+/// Kind of root container accepted by `ParseOptions::require_root`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RootKind {
+    #[default]
+    Any,
+    Object,
+    Array,
+}
+
+/// Policy for duplicate keys in an object.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// The last value wins.
+    #[default]
+    Last,
+    /// Values that are both objects are merged recursively, member by member;
+    /// otherwise the last value wins.
+    MergeObjects,
+    /// The repeated key fails with `Error::StrictViolation`.
+    Error,
+}
+
+impl ParseOptions {
+    /// JSON5-style parsing: comments, trailing commas, single quotes, unquoted keys
+    /// and `\x` escapes.
+    pub fn relaxed() -> ParseOptions {
+        ParseOptions {
+            comments: true,
+            trailing_commas: true,
+            single_quotes: true,
+            unquoted_keys: true,
+            hex_escapes: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    /// Strict RFC 8259 parsing: sets `strict`, and rejects duplicate keys with
+    /// `DuplicateKeys::Error`.
+    pub fn strict() -> ParseOptions {
+        ParseOptions {
+            strict: true,
+            duplicate_keys: DuplicateKeys::Error,
+            ..ParseOptions::default()
         }
     }
 }
@@ -22,72 +250,464 @@ impl Display for Error {
 extern crate nom;
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until},
-    character::complete::{digit1, multispace0},
-    error::ErrorKind,
-    multi::separated_list,
-    sequence::delimited,
-    IResult,
+    bytes::complete::{tag, take_till, take_until, take_while, take_while_m_n},
+    character::complete::{char, digit1, multispace0, multispace1},
+    combinator::{cut, map, map_res, not, recognize, value},
+    error::{ErrorKind, ParseError},
+    multi::many0_count,
+    sequence::tuple,
+    Err, IResult,
 };
 
-use crate::json::{Root, Value};
+use alloc::collections::BTreeSet;
+use alloc::sync::Arc;
+use core::cell::RefCell;
+
+use crate::json::{reserve_members, Array, Key, Object, Root, Value};
 
+/// Parses a JSON document. Malformed input of any kind is reported as an
+/// `Err`; parsing never panics.
 pub fn parse(s: &str) -> Result<Root, Error> {
-    if let Ok((s, j)) = parse_json(s) {
-        if let Ok((s, _)) = multispace0::<&str, (&str, ErrorKind)>(s) {
-            if s != "" {
-                return Err(Error::FoundUnTerminatedError);
+    parse_with_options(s, &ParseOptions::default())
+}
+
+/// Parses `s` into `value`, reusing the storage of its array or object when
+/// `s` has the same kind of root. See `Parser::parse_value_into`.
+pub fn parse_into(s: &str, value: &mut Value) -> Result<(), Error> {
+    Parser::default().parse_value_into(s, value)
+}
+
+pub fn parse_with_options(s: &str, options: &ParseOptions) -> Result<Root, Error> {
+    if let Some(max) = options.max_total_bytes {
+        if s.len() > max {
+            return Err(Error::InputTooLarge);
+        }
+    }
+    let (j, x) = parse_root(s, options, None)?;
+    let (x, _) = whitespace(x, options).map_err(|e| to_error(s, e))?;
+    if !x.is_empty() {
+        return Err(Error::FoundUnTerminatedError(s.len() - x.len()));
+    }
+    Ok(j)
+}
+
+/// Parses raw bytes, transcoding UTF-16 input marked by a byte order mark.
+/// Anything else is read as UTF-8, with or without a byte order mark.
+/// Positions in errors are byte offsets into the UTF-8 text.
+pub fn parse_detect_encoding(bytes: &[u8]) -> Result<Root, Error> {
+    let utf16 = |rest: &[u8], unit: fn([u8; 2]) -> u16| {
+        let chunks = rest.chunks_exact(2);
+        if !chunks.remainder().is_empty() {
+            return Err(Error::InvalidEncoding);
+        }
+        let units: Vec<u16> = chunks.map(|c| unit([c[0], c[1]])).collect();
+        String::from_utf16(&units).map_err(|_| Error::InvalidEncoding)
+    };
+    let text = match bytes {
+        [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes)?,
+        [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes)?,
+        [0xef, 0xbb, 0xbf, rest @ ..] | rest => core::str::from_utf8(rest)
+            .map_err(|_| Error::InvalidEncoding)?
+            .to_string(),
+    };
+    parse(&text)
+}
+
+/// Parses a leading JSON value and returns it with the unparsed remainder of `s`.
+pub fn parse_prefix(s: &str) -> Result<(Root, &str), Error> {
+    parse_root(s, &ParseOptions::default(), None)
+}
+
+/// Result of `parse_outcome`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOutcome {
+    pub root: Root,
+    /// Bytes up to the end of the root value, excluding whitespace after it.
+    pub consumed: usize,
+    /// The input after `consumed`, unless it is only whitespace.
+    pub trailing: Option<String>,
+}
+
+/// Parses a leading JSON value and reports where it ends, for mapping the
+/// result back to the source. Trailing content is not an error.
+pub fn parse_outcome(s: &str) -> Result<ParseOutcome, Error> {
+    let (root, rest) = parse_prefix(s)?;
+    let consumed = s.len() - rest.len();
+    let trailing = match rest.trim() {
+        "" => None,
+        _ => Some(rest.to_string()),
+    };
+    Ok(ParseOutcome {
+        root,
+        consumed,
+        trailing,
+    })
+}
+
+/// Parses a top-level array one element at a time, so that a huge array need
+/// not be held in memory at once. Only the opening `[` is checked up front; a
+/// syntax error later on is yielded in place of the element it occurs in, and
+/// ends the iteration.
+pub fn parse_array_stream(
+    s: &str,
+) -> Result<impl Iterator<Item = Result<Value, Error>> + '_, Error> {
+    let options = ParseOptions::default();
+    let (x, _) = whitespace(s, &options)
+        .and_then(|(x, _)| tag("[")(x))
+        .map_err(|e| to_error(s, e))?;
+    Ok(ArrayStream {
+        input: s,
+        open: s.len() - x.len() - 1,
+        rest: x,
+        options,
+        first: true,
+        done: false,
+    })
+}
+
+struct ArrayStream<'a> {
+    input: &'a str,
+    open: usize,
+    rest: &'a str,
+    options: ParseOptions,
+    first: bool,
+    done: bool,
+}
+
+impl<'a> ArrayStream<'a> {
+    // Parses the next element, or returns `None` after the closing `]`.
+    fn element(&mut self) -> Result<Option<Value>, Error> {
+        let options = &self.options;
+        let (x, _) = whitespace(self.rest, options).map_err(|e| to_error(self.input, e))?;
+        if x.is_empty() {
+            return Err(Error::UnclosedContainer(self.open));
+        }
+        if let Ok((x, _)) = tag::<&str, &str, Failure>("]")(x) {
+            let (x, _) = whitespace(x, options).map_err(|e| to_error(self.input, e))?;
+            if !x.is_empty() {
+                return Err(Error::FoundUnTerminatedError(self.input.len() - x.len()));
+            }
+            return Ok(None);
+        }
+        let item = |x| {
+            let x = if self.first { x } else { tag(",")(x)?.0 };
+            cut(|s| parse_value(s, 1, options, None))(x)
+        };
+        let (x, v) = unclosed(&self.input[self.open..], item(x), options)
+            .map_err(|e| to_error(self.input, e))?;
+        self.first = false;
+        self.rest = x;
+        Ok(Some(v))
+    }
+}
+
+impl<'a> Iterator for ArrayStream<'a> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.element().transpose();
+        self.done = !matches!(next, Some(Ok(_)));
+        next
+    }
+}
+
+impl core::str::FromStr for Root {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+/// Parses documents one after another, reusing allocations between them.
+#[derive(Debug, Default)]
+pub struct Parser {
+    options: ParseOptions,
+    members: Vec<Member>,
+    keys: Option<RefCell<KeyCache>>,
+}
+
+impl Parser {
+    pub fn new(options: &ParseOptions) -> Parser {
+        Parser {
+            options: options.clone(),
+            members: vec![],
+            keys: None,
+        }
+    }
+
+    /// Shares object keys between all the objects this parser builds, so that
+    /// each distinct key is allocated once. That covers a key repeated within
+    /// a document, such as a field of every record in a large array, as well
+    /// as keys repeated across documents, such as NDJSON records. Keys are
+    /// kept while some tree parsed by this parser still uses them.
+    pub fn intern_keys(mut self) -> Parser {
+        self.keys = Some(RefCell::default());
+        self
+    }
+
+    /// Parses `s` into `root`. When `s` has the same kind of root as `root`, the
+    /// container's storage is reused instead of allocating a new one. On error
+    /// the contents of `root` are unspecified.
+    pub fn parse_into(&mut self, s: &str, root: &mut Root) -> Result<(), Error> {
+        let parsed = self.parse_document(s, root);
+        if let Some(keys) = &self.keys {
+            keys.borrow_mut().prune();
+        }
+        parsed
+    }
+
+    fn parse_document(&mut self, s: &str, root: &mut Root) -> Result<(), Error> {
+        let options = &self.options;
+        if let Some(max) = options.max_total_bytes {
+            if s.len() > max {
+                return Err(Error::InputTooLarge);
+            }
+        }
+        check_root_kind(s, options)?;
+        let (x, _) = whitespace(s, options).map_err(|e| to_error(s, e))?;
+        let keys = self.keys.as_ref();
+        let members = &mut self.members;
+        let parsed = match (x.chars().next(), root) {
+            (Some('['), Root::Array(a)) => {
+                a.clear();
+                parse_array_into(x, a, options, keys)
+            }
+            (Some('{'), Root::Object(o)) => {
+                o.clear();
+                parse_object_into(x, o, members, options, keys)
             }
+            (_, root) => {
+                let (r, x) = parse_root(s, options, keys)?;
+                *root = r;
+                Ok((x, ()))
+            }
+        };
+        let (x, _) = parsed.map_err(|e| to_error(s, e))?;
+        let (x, _) = whitespace(x, options).map_err(|e| to_error(s, e))?;
+        if !x.is_empty() {
+            return Err(Error::FoundUnTerminatedError(s.len() - x.len()));
+        }
+        Ok(())
+    }
+
+    /// Like `parse_into`, for a `Value`. A scalar `value` is replaced.
+    pub fn parse_value_into(&mut self, s: &str, value: &mut Value) -> Result<(), Error> {
+        let mut root = core::mem::replace(value, Value::Null)
+            .try_into_root()
+            .unwrap_or_else(|| Root::Array(vec![]));
+        let parsed = self.parse_into(s, &mut root);
+        *value = root.into_value();
+        parsed
+    }
+}
+
+// The keys handed out by a `Parser` with `intern_keys`, by content.
+#[derive(Debug, Default)]
+struct KeyCache {
+    keys: BTreeSet<Key>,
+}
+
+type Keys<'k> = Option<&'k RefCell<KeyCache>>;
+
+impl KeyCache {
+    fn get(&mut self, key: &str) -> Key {
+        if let Some(k) = self.keys.get(key) {
+            return k.clone();
+        }
+        let k = Key::from(key);
+        self.keys.insert(k.clone());
+        k
+    }
+
+    // Drops the keys that no parsed tree uses any more.
+    fn prune(&mut self) {
+        self.keys.retain(|k| Arc::strong_count(k) > 1);
+    }
+}
+
+// A key as it goes into an object: shared through `keys` when interning.
+fn to_key(k: &str, keys: Keys) -> Key {
+    match keys {
+        Some(keys) => keys.borrow_mut().get(k),
+        None => Key::from(k),
+    }
+}
+
+fn parse_root<'a>(
+    s: &'a str,
+    options: &ParseOptions,
+    keys: Keys,
+) -> Result<(Root, &'a str), Error> {
+    check_root_kind(s, options)?;
+    match parse_json(s, options, keys) {
+        Ok((x, j)) => Ok((j, x)),
+        Err(e) => Err(to_error(s, e)),
+    }
+}
+
+// Checks the opening bracket of the root up front, before parsing all of it.
+fn check_root_kind(s: &str, options: &ParseOptions) -> Result<(), Error> {
+    let (x, _) = whitespace(s, options).map_err(|e| to_error(s, e))?;
+    match (options.require_root, x.chars().next()) {
+        (RootKind::Object, Some('[')) | (RootKind::Array, Some('{')) => {
+            Err(Error::UnexpectedRootKind(s.len() - x.len()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// What a failed parser reports, once located in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Reason {
+    Syntax,
+    DepthLimitExceeded,
+    StringTooLong,
+    UnclosedContainer,
+    MissingColon,
+    UnexpectedColon,
+    UnexpectedBom,
+    Strict(&'static str),
+}
+
+impl Reason {
+    fn at(self, p: usize) -> Error {
+        match self {
+            Reason::Syntax => Error::ParseError(p),
+            Reason::DepthLimitExceeded => Error::DepthLimitExceeded(p),
+            Reason::StringTooLong => Error::StringTooLong(p),
+            Reason::UnclosedContainer => Error::UnclosedContainer(p),
+            Reason::MissingColon => Error::MissingColon(p),
+            Reason::UnexpectedColon => Error::UnexpectedColon(p),
+            Reason::Strict(reason) => Error::StrictViolation(p, reason),
+            Reason::UnexpectedBom => Error::UnexpectedBom(p),
+        }
+    }
+}
+
+/// The error type of the parsers: the input left where parsing failed, and
+/// what to report. Errors from nom's own combinators are `Reason::Syntax`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Failure<'a> {
+    pub(crate) input: &'a str,
+    pub(crate) reason: Reason,
+}
+
+impl<'a> ParseError<&'a str> for Failure<'a> {
+    fn from_error_kind(input: &'a str, _: ErrorKind) -> Self {
+        Failure {
+            input,
+            reason: Reason::Syntax,
         }
-        Ok(j)
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+pub(crate) type PResult<'a, O> = IResult<&'a str, O, Failure<'a>>;
+
+// Fails without backtracking, reporting `reason` at `input`.
+fn fail<O>(input: &str, reason: Reason) -> PResult<'_, O> {
+    Err(Err::Failure(Failure { input, reason }))
+}
+
+fn to_error(s: &str, e: Err<Failure>) -> Error {
+    match e {
+        Err::Error(f) | Err::Failure(f) => f.reason.at(s.len() - f.input.len()),
+        Err::Incomplete(_) => Error::ParseError(s.len()),
+    }
+}
+
+fn parse_json<'a>(s: &'a str, options: &ParseOptions, keys: Keys) -> PResult<'a, Root> {
+    let capacity = root_capacity_hint(s);
+    alt((
+        map(
+            move |s| parse_array_with_capacity(s, capacity, 1, options, keys),
+            Root::Array,
+        ),
+        map(
+            move |s| parse_object_with_capacity(s, capacity, 1, options, keys),
+            Root::Object,
+        ),
+    ))(s)
+}
+
+// Skips insignificant whitespace, and comments too when they are enabled.
+pub(crate) fn parse_whitespace<'a>(s: &'a str, options: &ParseOptions) -> PResult<'a, &'a str> {
+    if options.comments {
+        recognize(many0_count(alt((multispace1, parse_comment))))(s)
     } else {
-        return Err(Error::ParseError);
+        multispace0(s)
     }
 }
-fn parse_json(s: &str) -> IResult<&str, Root> {
-    let (s, v) = alt((parse_array, parse_object))(s)?;
 
-    match v {
-        Value::Object(o) => Ok((s, Root::Object(o))),
-        Value::Array(a) => Ok((s, Root::Array(a))),
-        _ => unreachable!(),
+// Whitespace between tokens, which must not be followed by a byte order mark.
+fn whitespace<'a>(s: &'a str, options: &ParseOptions) -> PResult<'a, &'a str> {
+    let (x, ws) = parse_whitespace(s, options)?;
+    if x.starts_with('\u{feff}') {
+        return fail(x, Reason::UnexpectedBom);
     }
+    Ok((x, ws))
 }
 
-fn parse_value(s: &str) -> IResult<&str, Value> {
-    let (s, _) = multispace0(s)?;
+// A line comment runs to the next newline or the end of the input; a block
+// comment must be closed.
+pub(crate) fn parse_comment(s: &str) -> PResult<'_, &str> {
     alt((
-        parse_null,
+        recognize(tuple((tag("//"), take_till(|c| c == '\n')))),
+        recognize(tuple((tag("/*"), take_until("*/"), tag("*/")))),
+    ))(s)
+}
+
+// `depth` is the number of containers enclosing the value.
+fn parse_value<'a>(
+    s: &'a str,
+    depth: usize,
+    options: &ParseOptions,
+    keys: Keys,
+) -> PResult<'a, Value> {
+    let (s, _) = whitespace(s, options)?;
+    alt((
+        |s| parse_null(s, options),
         parse_boolean,
-        parse_number,
-        parse_string,
-        parse_array,
-        parse_object,
+        |s| parse_number(s, options),
+        |s| parse_string(s, options),
+        |s| parse_array(s, depth + 1, options, keys),
+        |s| parse_object(s, depth + 1, options, keys),
     ))(s)
 }
 
-fn parse_null(s: &str) -> IResult<&str, Value> {
-    let (s, _) = tag("null")(s)?;
+fn parse_null<'a>(s: &'a str, options: &ParseOptions) -> PResult<'a, Value> {
+    let (s, _) = if options.undefined_as_null && !options.strict {
+        alt((tag("null"), tag("undefined")))(s)?
+    } else {
+        tag("null")(s)?
+    };
     Ok((s, Value::Null))
 }
-fn parse_boolean(s: &str) -> IResult<&str, Value> {
+fn parse_boolean(s: &str) -> PResult<'_, Value> {
     match alt((tag("true"), tag("false")))(s)? {
         (s, "true") => Ok((s, Value::Boolean(true))),
         (s, _) => Ok((s, Value::Boolean(false))),
     }
 }
 
-fn parse_digits_with_sign(s: &str) -> IResult<&str, String> {
+fn parse_digits_with_sign(s: &str) -> PResult<'_, String> {
     let (s, suffix) = alt((tag("+"), tag("-"), tag("")))(s)?;
 
     let (s, v1) = digit1(s)?;
     Ok((s, format!("{}{}", suffix, v1)))
 }
 
-fn parse_number_with_sign(s: &str) -> IResult<&str, String> {
+fn parse_number_with_sign(s: &str) -> PResult<'_, String> {
     let (s, v1) = parse_digits_with_sign(s)?;
 
-    match tag::<&str, &str, (&str, ErrorKind)>(".")(s) {
+    match tag::<&str, &str, Failure>(".")(s) {
         Ok((s, _)) => {
             let (s, v2) = digit1(s)?;
             Ok((s, format!("{}.{}", v1, v2)))
@@ -96,178 +716,816 @@ fn parse_number_with_sign(s: &str) -> IResult<&str, String> {
     }
 }
 
-fn parse_number(s: &str) -> IResult<&str, Value> {
-    let (s, v1) = parse_number_with_sign(s)?;
-    if let Ok((x, e)) = alt((
-        tag::<&str, &str, (&str, ErrorKind)>("e"),
-        tag::<&str, &str, (&str, ErrorKind)>("E"),
-    ))(s)
+pub(crate) fn parse_number<'a>(s: &'a str, options: &ParseOptions) -> PResult<'a, Value> {
+    // JSON only allows a minus sign in front of the mantissa, and no leading zeros.
+    if options.strict {
+        not(tag("+"))(s)?;
+        let digits = s.strip_prefix('-').unwrap_or(s).as_bytes();
+        if digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit() {
+            return fail(s, Reason::Strict("leading zero"));
+        }
+    }
+    let (x, v1) = parse_number_with_sign(s)?;
+    let (x, v) = match alt((
+        tag::<&str, &str, Failure>("e"),
+        tag::<&str, &str, Failure>("E"),
+    ))(x)
     {
-        let (x, v2) = parse_digits_with_sign(x)?;
-        return Ok((
-            x,
-            Value::Number(format!("{}{}{}", v1, e, v2).parse().unwrap()),
-        ));
+        Ok((x, e)) => {
+            let (x, v2) = parse_digits_with_sign(x)?;
+            (x, format!("{}{}{}", v1, e, v2))
+        }
+        _ => (x, v1),
+    };
+
+    // Numbers that overflow an f64 (e.g. `1e999`) are rejected rather than
+    // read as infinity, since infinity cannot be written back as JSON.
+    // Underflow rounds towards zero as usual.
+    match v.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok((x, Value::Number(n))),
+        _ => Err(Err::Error(Failure::from_error_kind(s, ErrorKind::Float))),
     }
-    Ok((s, Value::Number(v1.parse().unwrap())))
 }
 
-fn parse_string(s: &str) -> IResult<&str, Value> {
+fn parse_string<'a>(s: &'a str, options: &ParseOptions) -> PResult<'a, Value> {
     let (s, _) = multispace0(s)?;
 
-    match delimited(tag("\""), take_until("\""), tag("\""))(s)? {
-        (s, v) => Ok((s, Value::String(v.to_string()))),
-    }
+    let (s, v) = parse_str(s, options)?;
+    Ok((s, Value::String(v)))
 }
 
-fn parse_array(s: &str) -> IResult<&str, Value> {
-    let (s, _) = multispace0(s)?;
+// Strings over `max_string_len` fail at the opening quote with `StringTooLong`.
+// The check runs while decoding, so an oversized string is never fully buffered.
+pub(crate) fn parse_str<'a>(s: &'a str, options: &ParseOptions) -> PResult<'a, String> {
+    let start = s;
+    let quote = if options.single_quotes && s.starts_with('\'') {
+        '\''
+    } else {
+        '"'
+    };
+    let (mut s, _) = char(quote)(s)?;
 
-    let (s, _) = tag("[")(s)?;
+    let too_long = |len: usize| matches!(options.max_string_len, Some(max) if len > max);
+    let mut v = String::new();
+    loop {
+        let (x, chunk) = take_till(|c| c == quote || c == '\\')(s)?;
+        if options.strict {
+            if let Some(i) = chunk.find(|c: char| c < ' ') {
+                return fail(&s[i..], Reason::Strict("control character in string"));
+            }
+        }
+        if too_long(v.len() + chunk.len()) {
+            return fail(start, Reason::StringTooLong);
+        }
+        v.push_str(chunk);
 
-    let (s, _) = multispace0(s)?;
+        if let Ok((x, _)) = char::<&str, Failure>(quote)(x) {
+            return Ok((x, v));
+        }
+        let (x, c) = if options.single_quotes && x.starts_with("\\'") {
+            (&x[2..], '\'')
+        } else if options.hex_escapes && x.starts_with("\\x") {
+            parse_hex_escape(&x[2..])?
+        } else {
+            parse_escape(x)?
+        };
+        v.push(c);
+        if too_long(v.len()) {
+            return fail(start, Reason::StringTooLong);
+        }
+        s = x;
+    }
+}
 
-    let (s, v) = match separated_list(tag(","), parse_value)(s) {
-        Ok((s, x)) => (s, Some(x)),
-        _ => (s, None),
-    };
+/// Decodes the escapes in the body of a JSON string, given without the
+/// surrounding quotes. An invalid escape or a bare `"` is reported at its byte offset.
+pub fn unescape(s: &str) -> Result<String, Error> {
+    let mut v = String::with_capacity(s.len());
+    let mut rest = s;
+    loop {
+        let i = rest.find(&['"', '\\'][..]).unwrap_or(rest.len());
+        v.push_str(&rest[..i]);
+        if i == rest.len() {
+            return Ok(v);
+        }
+        let (x, c) = parse_escape(&rest[i..]).map_err(|e| to_error(s, e))?;
+        v.push(c);
+        rest = x;
+    }
+}
 
-    let (s, _) = multispace0(s)?;
+// `\/` is an optional escape in JSON. It decodes to `/`, and the serializer
+// always writes `/` back unescaped.
+fn parse_escape(s: &str) -> PResult<'_, char> {
+    let (s, _) = tag("\\")(s)?;
+    alt((
+        value('"', tag("\"")),
+        value('\\', tag("\\")),
+        value('/', tag("/")),
+        value('\u{08}', tag("b")),
+        value('\u{0c}', tag("f")),
+        value('\n', tag("n")),
+        value('\r', tag("r")),
+        value('\t', tag("t")),
+        parse_unicode_escape,
+    ))(s)
+}
 
-    let (s, _) = tag("]")(s)?;
+fn parse_hex_escape(s: &str) -> PResult<'_, char> {
+    map(
+        map_res(take_while_m_n(2, 2, |c: char| c.is_ascii_hexdigit()), |h| {
+            u8::from_str_radix(h, 16)
+        }),
+        char::from,
+    )(s)
+}
 
-    match v {
-        Some(v) => Ok((s, Value::Array(v))),
-        None => Ok((s, Value::Array(vec![]))),
-    }
+fn parse_hex4(s: &str) -> PResult<'_, u32> {
+    map_res(take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()), |h| {
+        u32::from_str_radix(h, 16)
+    })(s)
 }
 
-fn parse_kvp(s: &str) -> IResult<&str, (String, Value)> {
-    let (s, _) = multispace0(s)?;
+fn parse_unicode_escape(s: &str) -> PResult<'_, char> {
+    let (s, _) = tag("u")(s)?;
+    let (x, high) = parse_hex4(s)?;
 
-    let (s, k) = parse_string(s)?;
+    let (x, code) = if (0xD800..0xDC00).contains(&high) {
+        let (x, _) = tag("\\u")(x)?;
+        let (x, low) = parse_hex4(x)?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(Err::Error(Failure::from_error_kind(s, ErrorKind::Char)));
+        }
+        (x, 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+    } else {
+        (x, high)
+    };
 
-    let (s, _) = multispace0(s)?;
-    let (s, _) = tag(":")(s)?;
+    match core::char::from_u32(code) {
+        Some(c) => Ok((x, c)),
+        None => Err(Err::Error(Failure::from_error_kind(s, ErrorKind::Char))),
+    }
+}
 
-    let (s, _) = multispace0(s)?;
-    let (s, v) = parse_value(s)?;
+// Capacity heuristic: the root container is the one most likely to be large
+// (data dumps, log batches), so its elements or members are counted up front
+// with a single pass over the top-level separators and the result is reserved
+// exactly. Nested containers start empty and grow on demand, since scanning
+// each of them would make parsing quadratic in the nesting depth.
+fn root_capacity_hint(s: &str) -> usize {
+    let mut depth = 0;
+    let mut separators = 0;
+    let mut in_string = false;
+    let mut escaped = false;
 
-    match k {
-        Value::String(k) => Ok((s, (k, v))),
-        _ => unreachable!(),
+    for b in s.bytes() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if b == b'\\' {
+                escaped = true;
+            } else if b == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => {
+                if depth <= 1 {
+                    break;
+                }
+                depth -= 1;
+            }
+            b',' if depth == 1 => separators += 1,
+            _ => {}
+        }
     }
+    separators + 1
 }
 
-fn parse_object(s: &str) -> IResult<&str, Value> {
-    let (s, _) = multispace0(s)?;
+fn parse_array<'a>(
+    s: &'a str,
+    depth: usize,
+    options: &ParseOptions,
+    keys: Keys,
+) -> PResult<'a, Value> {
+    map(
+        |s| parse_array_with_capacity(s, 0, depth, options, keys),
+        Value::Array,
+    )(s)
+}
 
-    let (s, _) = tag("{")(s)?;
+fn parse_array_with_capacity<'a>(
+    s: &'a str,
+    capacity: usize,
+    depth: usize,
+    options: &ParseOptions,
+    keys: Keys,
+) -> PResult<'a, Array> {
+    let (s, _) = whitespace(s, options)?;
 
-    let (s, _) = multispace0(s)?;
+    let (x, _) = tag("[")(s)?;
+    check_depth(s, depth, options)?;
 
-    let (s, kvs) = match separated_list(tag(","), parse_kvp)(s) {
-        Ok((s, x)) => (s, Some(x)),
-        _ => (s, None),
-    };
+    let r = cut(|s| {
+        parse_list(s, "]", capacity, options, |s| {
+            parse_value(s, depth, options, keys)
+        })
+    })(x);
+    unclosed(s, stray_colon(r, options), options)
+}
 
-    let (s, _) = multispace0(s)?;
+fn parse_array_into<'a>(
+    s: &'a str,
+    a: &mut Array,
+    options: &ParseOptions,
+    keys: Keys,
+) -> PResult<'a, ()> {
+    let (x, _) = tag("[")(s)?;
+    check_depth(s, 1, options)?;
 
-    let (s, _) = tag("}")(s)?;
+    let r = to_failure(parse_list_into(x, "]", 0, a, options, |s| {
+        parse_value(s, 1, options, keys)
+    }));
+    unclosed(s, stray_colon(r, options), options)
+}
 
-    let mut map = HashMap::new();
-    if let Some(x) = kvs {
-        for (k, v) in x {
-            map.insert(k, v);
+// Turns a syntax error at the end of the input into `UnclosedContainer` at the
+// opening bracket `s` of the container it is in. An enclosing container sees a
+// failure inside the input and leaves it alone, so the innermost unclosed
+// container is reported.
+fn unclosed<'a, O>(s: &'a str, r: PResult<'a, O>, options: &ParseOptions) -> PResult<'a, O> {
+    match r {
+        Err(Err::Failure(f))
+            if matches!(f.reason, Reason::Syntax | Reason::MissingColon)
+                && at_end(f.input, options) =>
+        {
+            fail(s, Reason::UnclosedContainer)
         }
+        r => r,
     }
-    Ok((s, Value::Object(map)))
 }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
+// Whether only whitespace and comments are left, the last of which may be a
+// block comment cut off by the end of the input.
+fn at_end(s: &str, options: &ParseOptions) -> bool {
+    match parse_whitespace(s, options) {
+        Ok((rest, _)) => {
+            rest.is_empty() || (options.comments && rest.starts_with("/*") && !rest.contains("*/"))
+        }
+        Err(_) => false,
+    }
+}
 
-    use crate::combinator::{parse, parse_value, Error};
-    use crate::json::Root;
-    use crate::json::Value::{Array, Boolean, Number, Object, String};
-    use nom::{error::ErrorKind, Err};
-
-    macro_rules! hash {
-        ( $( $t:expr),* ) => {
-            {
-                let mut temp_hash = HashMap::new();
-                $(
-                    temp_hash.insert($t.0, $t.1);
-                )*
-                temp_hash
+// Reports a syntax error in an array at a `:` as `UnexpectedColon`.
+fn stray_colon<'a, O>(r: PResult<'a, O>, options: &ParseOptions) -> PResult<'a, O> {
+    match r {
+        Err(Err::Failure(f)) if f.reason == Reason::Syntax => {
+            match parse_whitespace(f.input, options) {
+                Ok((x, _)) if x.starts_with(':') => fail(x, Reason::UnexpectedColon),
+                _ => Err(Err::Failure(f)),
             }
-        };
+        }
+        r => r,
     }
+}
 
-    #[test]
-    fn boolean() {
-        assert_eq!(parse_value("true"), Ok(("", Boolean(true))));
-        assert_eq!(parse_value("false-m"), Ok(("-m", Boolean(false))));
-        assert_eq!(parse_value("xxx"), Err(Err::Error(("xxx", ErrorKind::Tag))));
+// Fails at the opening bracket `s` of a container nested deeper than allowed.
+fn check_depth<'a>(s: &'a str, depth: usize, options: &ParseOptions) -> PResult<'a, ()> {
+    match options.max_depth {
+        Some(max) if depth > max => fail(s, Reason::DepthLimitExceeded),
+        _ => Ok((s, ())),
     }
-    #[test]
-    fn number() {
-        assert_eq!(parse_value("1234567"), Ok(("", Number(1234567.0))));
-        assert_eq!(parse_value("123-4567"), Ok(("-4567", Number(123.0))));
-        assert_eq!(parse_value("123.4567"), Ok(("", Number(123.4567))));
-        assert_eq!(parse_value("-123.4567"), Ok(("", Number(-123.4567))));
-        assert_eq!(parse_value("10e0"), Ok(("", Number(10.0))));
-        assert_eq!(parse_value("10e-10"), Ok(("", Number(0.000000001))));
-        assert_eq!(parse_value("-1.2e-10"), Ok(("", Number(-0.00000000012))));
-        assert_eq!(parse_value("x"), Err(Err::Error(("x", ErrorKind::Tag))));
+}
+
+fn parse_list<'a, T, F>(
+    s: &'a str,
+    close: &'static str,
+    capacity: usize,
+    options: &ParseOptions,
+    item: F,
+) -> PResult<'a, Vec<T>>
+where
+    F: Fn(&'a str) -> PResult<'a, T>,
+{
+    let mut v = Vec::new();
+    let (s, _) = parse_list_into(s, close, capacity, &mut v, options, item)?;
+    Ok((s, v))
+}
+
+// Appends the items to `v`, reserving `capacity` only once the list turns out non-empty.
+fn parse_list_into<'a, T, F>(
+    s: &'a str,
+    close: &'static str,
+    capacity: usize,
+    v: &mut Vec<T>,
+    options: &ParseOptions,
+    item: F,
+) -> PResult<'a, ()>
+where
+    F: Fn(&'a str) -> PResult<'a, T>,
+{
+    let (s, _) = whitespace(s, options)?;
+
+    if let Ok((s, _)) = tag::<&str, &str, Failure>(close)(s) {
+        return Ok((s, ()));
     }
 
-    #[test]
-    fn string() {
-        assert_eq!(parse_value("\"x\""), Ok(("", String("x".to_string()))));
-        assert_eq!(
-            parse_value("\"true\""),
-            Ok(("", String("true".to_string())))
-        );
-        assert_eq!(parse_value("\"\""), Ok(("", String("".to_string()))));
-        assert_eq!(parse_value("\"x"), Err(Err::Error(("\"x", ErrorKind::Tag))));
-        assert_eq!(parse_value("x"), Err(Err::Error(("x", ErrorKind::Tag))));
+    v.reserve(capacity);
+
+    let (mut s, x) = cut(&item)(s)?;
+    v.push(x);
+    loop {
+        match parse_separator(s, options) {
+            Ok((x, _)) => {
+                if options.trailing_commas {
+                    let (x, _) = whitespace(x, options)?;
+                    if let Ok((x, _)) = tag::<&str, &str, Failure>(close)(x) {
+                        return Ok((x, ()));
+                    }
+                }
+                let (x, y) = cut(&item)(x)?;
+                v.push(y);
+                s = x;
+            }
+            Err(Err::Error(_)) => break,
+            Err(e) => return Err(e),
+        }
     }
-    #[test]
-    fn array() {
-        assert_eq!(
-            parse_value("[\"string\"]"),
-            Ok(("", Array(vec![String("string".to_string())])))
-        );
-        assert_eq!(parse_value("[true]"), Ok(("", Array(vec![Boolean(true)]))));
-        assert_eq!(parse_value("[1]"), Ok(("", Array(vec![Number(1.0)]))));
-        assert_eq!(parse_value("[]"), Ok(("", Array(vec![]))));
-        assert_eq!(parse_value("[[]]"), Ok(("", Array(vec![Array(vec![])]))));
-        assert_eq!(
-            parse_value("[true,false]"),
-            Ok(("", Array(vec![Boolean(true), Boolean(false)])))
-        );
-        assert_eq!(parse_value(" [true]"), Ok(("", Array(vec![Boolean(true)]))));
-        assert_eq!(parse_value("[ true]"), Ok(("", Array(vec![Boolean(true)]))));
-        assert_eq!(parse_value("[true ]"), Ok(("", Array(vec![Boolean(true)]))));
-        assert_eq!(
-            parse_value("[true] "),
-            Ok((" ", Array(vec![Boolean(true)])))
-        );
+
+    let (s, _) = whitespace(s, options)?;
+
+    let (s, _) = tag(close)(s)?;
+
+    Ok((s, ()))
+}
+
+// Same as `cut`, for results of parsers that can't be passed as `Fn`.
+fn to_failure<I, O, E>(r: IResult<I, O, E>) -> IResult<I, O, E> {
+    match r {
+        Err(Err::Error(e)) => Err(Err::Failure(e)),
+        r => r,
     }
-    #[test]
-    fn object() {
-        assert_eq!(
-            parse_value("{\"x\":\"y\"}"),
-            Ok((
-                "",
-                Object(hash![("x".to_string(), String("y".to_string()))])
-            ))
-        );
-        assert_eq!(
-            parse_value("{\"x\":\"y\",\"z\":\"w\"}"),
-            Ok((
+}
+
+fn parse_separator<'a>(s: &'a str, options: &ParseOptions) -> PResult<'a, &'a str> {
+    let (s, _) = whitespace(s, options)?;
+    tag(",")(s)
+}
+
+// An object member as parsed, before it is inserted.
+struct Member {
+    // Length of the input left at the key, to locate it in errors.
+    rest: usize,
+    key: Key,
+    value: Value,
+}
+
+impl core::fmt::Debug for Member {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:?}: {:?}", self.key, self.value)
+    }
+}
+
+fn parse_kvp<'a>(
+    s: &'a str,
+    depth: usize,
+    options: &ParseOptions,
+    keys: Keys,
+) -> PResult<'a, Member> {
+    let (key, _) = whitespace(s, options)?;
+
+    let (s, k) = match plain_key(key, options) {
+        Some((s, body)) => (s, to_key(body, keys)),
+        None => {
+            let (s, k) = if options.unquoted_keys {
+                alt((|s| parse_str(s, options), parse_identifier))(key)?
+            } else {
+                parse_str(key, options)?
+            };
+            (s, to_key(&k, keys))
+        }
+    };
+    if options.reject_nul_in_keys && k.contains('\0') {
+        return fail(key, Reason::Strict("NUL in key"));
+    }
+
+    let (s, _) = whitespace(s, options)?;
+    let (s, _) = match tag::<&str, &str, Failure>(":")(s) {
+        Ok(r) => r,
+        Err(_) => return fail(s, Reason::MissingColon),
+    };
+
+    let (s, v) = parse_value(s, depth, options, keys)?;
+
+    Ok((
+        s,
+        Member {
+            rest: key.len(),
+            key: k,
+            value: v,
+        },
+    ))
+}
+
+// The body of a quoted key without escapes or control characters, which is
+// taken from the input as it is. Anything else is left to `parse_str`, which
+// also reports the errors.
+fn plain_key<'a>(s: &'a str, options: &ParseOptions) -> Option<(&'a str, &'a str)> {
+    let body = s.strip_prefix('"')?;
+    let end = body.find(|c: char| c == '"' || c == '\\' || c < ' ')?;
+    let too_long = matches!(options.max_string_len, Some(max) if end > max);
+    if too_long || !body[end..].starts_with('"') {
+        return None;
+    }
+    Some((&body[end + 1..], &body[..end]))
+}
+
+fn parse_identifier(s: &str) -> PResult<'_, String> {
+    let start = |c: char| c.is_ascii_alphabetic() || c == '_' || c == '$';
+    let (s, k) = recognize(tuple((
+        take_while_m_n(1, 1, start),
+        take_while(|c: char| start(c) || c.is_ascii_digit()),
+    )))(s)?;
+    Ok((s, k.to_string()))
+}
+
+fn parse_object<'a>(
+    s: &'a str,
+    depth: usize,
+    options: &ParseOptions,
+    keys: Keys,
+) -> PResult<'a, Value> {
+    map(
+        |s| parse_object_with_capacity(s, 0, depth, options, keys),
+        Value::Object,
+    )(s)
+}
+
+fn parse_object_with_capacity<'a>(
+    s: &'a str,
+    capacity: usize,
+    depth: usize,
+    options: &ParseOptions,
+    keys: Keys,
+) -> PResult<'a, Object> {
+    let (s, _) = whitespace(s, options)?;
+
+    let (x, _) = tag("{")(s)?;
+    check_depth(s, depth, options)?;
+
+    let r = cut(|s| {
+        parse_list(s, "}", capacity, options, |s| {
+            parse_kvp(s, depth, options, keys)
+        })
+    })(x);
+    let (x, members) = unclosed(s, r, options)?;
+
+    let mut map = Object::new();
+    reserve_members(&mut map, members.len());
+    insert_members(s, &mut map, members, options)?;
+    Ok((x, map))
+}
+
+// Collects the members in `members` first so that its storage can be reused too.
+fn parse_object_into<'a>(
+    s: &'a str,
+    o: &mut Object,
+    members: &mut Vec<Member>,
+    options: &ParseOptions,
+    keys: Keys,
+) -> PResult<'a, ()> {
+    let (x, _) = tag("{")(s)?;
+    check_depth(s, 1, options)?;
+
+    members.clear();
+    let r = to_failure(parse_list_into(x, "}", 0, members, options, |s| {
+        parse_kvp(s, 1, options, keys)
+    }));
+    let (x, _) = unclosed(s, r, options)?;
+
+    reserve_members(o, members.len());
+    insert_members(s, o, members.drain(..), options)?;
+    Ok((x, ()))
+}
+
+// Inserts the members of the object starting at `s`, failing at the first
+// repeated key when `DuplicateKeys::Error` is set.
+fn insert_members<'a, I>(
+    s: &'a str,
+    o: &mut Object,
+    members: I,
+    options: &ParseOptions,
+) -> PResult<'a, ()>
+where
+    I: IntoIterator<Item = Member>,
+{
+    for m in members {
+        if options.duplicate_keys == DuplicateKeys::Error && o.contains_key(&m.key) {
+            return fail(&s[s.len() - m.rest..], Reason::Strict("duplicate key"));
+        }
+        insert_member(o, m.key, m.value, options);
+    }
+    Ok((s, ()))
+}
+
+fn insert_member(o: &mut Object, k: Key, v: Value, options: &ParseOptions) {
+    match (options.duplicate_keys, o.get_mut(&k), v) {
+        (DuplicateKeys::MergeObjects, Some(Value::Object(old)), Value::Object(new)) => {
+            for (k, v) in new {
+                insert_member(old, k, v, options);
+            }
+        }
+        (_, _, v) => {
+            o.insert(k, v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combinator::{
+        char_index, line_column, parse, parse_array_stream, parse_detect_encoding, parse_into,
+        parse_outcome, parse_prefix, parse_value, parse_with_options, DuplicateKeys, Error,
+        ParseOptions, Parser, RootKind,
+    };
+    use crate::combinator::{Failure, PResult, Reason};
+    use crate::json::Root;
+    use crate::json::Value;
+    use crate::json::Value::{Array, Boolean, Null, Number, Object, String};
+    use nom::Err;
+
+    fn value(s: &str) -> PResult<'_, Value> {
+        parse_value(s, 0, &ParseOptions::default(), None)
+    }
+
+    fn syntax(input: &str) -> Err<Failure<'_>> {
+        Err::Error(Failure {
+            input,
+            reason: Reason::Syntax,
+        })
+    }
+
+    #[test]
+    fn boolean() {
+        assert_eq!(value("true"), Ok(("", Boolean(true))));
+        assert_eq!(value("false-m"), Ok(("-m", Boolean(false))));
+        assert_eq!(value("xxx"), Err(syntax("xxx")));
+    }
+    #[test]
+    fn number() {
+        assert_eq!(value("1234567"), Ok(("", Number(1234567.0))));
+        assert_eq!(value("123-4567"), Ok(("-4567", Number(123.0))));
+        assert_eq!(value("123.4567"), Ok(("", Number(123.4567))));
+        assert_eq!(value("-123.4567"), Ok(("", Number(-123.4567))));
+        assert_eq!(value("10e0"), Ok(("", Number(10.0))));
+        assert_eq!(value("10e-10"), Ok(("", Number(0.000000001))));
+        assert_eq!(value("-1.2e-10"), Ok(("", Number(-0.00000000012))));
+        assert_eq!(value("x"), Err(syntax("x")));
+    }
+
+    #[test]
+    fn number_overflow() {
+        assert_eq!(value("1e999"), Err(syntax("1e999")));
+        assert_eq!(value("1e-999"), Ok(("", Number(0.0))));
+        assert_eq!(parse("[1, 1e999]"), Err(Error::ParseError(4)));
+        assert_eq!(parse("{\"a\": -1E400}"), Err(Error::ParseError(6)));
+    }
+
+    #[test]
+    fn strict_plus_sign() {
+        let strict = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+
+        assert_eq!(
+            parse_with_options("[+5]", &strict),
+            Err(Error::ParseError(1))
+        );
+        assert_eq!(
+            parse_with_options("[5, -5, 5e+3, 5E-3]", &strict),
+            Ok(Root::Array(vec![
+                Number(5.0),
+                Number(-5.0),
+                Number(5000.0),
+                Number(0.005)
+            ]))
+        );
+        assert_eq!(parse("[+5]"), Ok(Root::Array(vec![Number(5.0)])));
+    }
+
+    #[test]
+    fn comments() {
+        let options = ParseOptions {
+            comments: true,
+            ..ParseOptions::default()
+        };
+        let input = "// head\n[1, /* two */ 2 // tail\n, 3]/**/";
+
+        assert_eq!(
+            parse_with_options(input, &options),
+            Ok(Root::Array(vec![Number(1.0), Number(2.0), Number(3.0)]))
+        );
+        assert_eq!(parse(input), Err(Error::ParseError(0)));
+        assert_eq!(
+            parse_with_options("[1 /* open", &options),
+            Err(Error::UnclosedContainer(0))
+        );
+        assert_eq!(
+            parse_with_options("{\"a\": [1, /* open", &options),
+            Err(Error::UnclosedContainer(6))
+        );
+        assert_eq!(
+            parse_with_options("[1 /* open */ x", &options),
+            Err(Error::ParseError(14))
+        );
+    }
+    #[test]
+    fn comments_at_eof() {
+        let options = ParseOptions {
+            comments: true,
+            ..ParseOptions::default()
+        };
+        let one = Ok(Root::Array(vec![Number(1.0)]));
+
+        assert_eq!(parse_with_options("[1] // tail", &options), one);
+        assert_eq!(parse_with_options("[1]//", &options), one);
+        assert_eq!(parse_with_options("[1] /* tail */", &options), one);
+        assert_eq!(parse_with_options("[1]/**/", &options), one);
+        assert_eq!(parse_with_options("[1] // a\n// b", &options), one);
+        assert_eq!(
+            parse_with_options("[1 // tail", &options),
+            Err(Error::UnclosedContainer(0))
+        );
+        assert_eq!(
+            parse_with_options("[1] /* tail *", &options),
+            Err(Error::FoundUnTerminatedError(4))
+        );
+    }
+
+    #[test]
+    fn malformed_never_panics() {
+        let corpus = [
+            "",
+            " ",
+            "-",
+            "1e",
+            "1e999",
+            "[-]",
+            "[1e]",
+            "[1e+]",
+            "[1e999]",
+            "[-1e999]",
+            "[.5]",
+            "[1.]",
+            "[01.e5]",
+            "[--1]",
+            "[+-1]",
+            "[1e5e5]",
+            "[\"\\u\"]",
+            "[\"\\u12\"]",
+            "[\"\\ud800\\u0041\"]",
+            "[\"\\udc00\"]",
+            "[\"\\",
+            "[\"é",
+            "{\"a\"",
+            "{\"a\":",
+            "{",
+            "[",
+            "]",
+            "}",
+            "[1,,2]",
+            "[,]",
+            "{,}",
+            "{:1}",
+            "[nul]",
+            "[tru]",
+            "\u{feff}[]",
+            "[1,\u{feff}2]",
+            "[1]]",
+            "[\u{0}]",
+            "/* */[]",
+            "[1 // x\n]",
+            "{\"a\":1,\"a\"}",
+        ];
+        let options = [
+            ParseOptions::default(),
+            ParseOptions {
+                strict: true,
+                comments: true,
+                ..ParseOptions::default()
+            },
+        ];
+
+        for input in corpus.iter() {
+            for o in options.iter() {
+                let got = std::panic::catch_unwind(|| parse_with_options(input, o));
+                match got {
+                    Ok(Err(_)) => {}
+                    Ok(Ok(root)) if o.comments => {
+                        assert!(input.contains("/"), "{:?} parsed to {:?}", input, root)
+                    }
+                    got => panic!("Want Err for {:?}, got {:?}", input, got),
+                }
+                assert!(std::panic::catch_unwind(
+                    || crate::lexer::Lexer::with_options(input, o).count()
+                )
+                .is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn string() {
+        assert_eq!(value("\"x\""), Ok(("", String("x".to_string()))));
+        assert_eq!(value("\"true\""), Ok(("", String("true".to_string()))));
+        assert_eq!(value("\"\""), Ok(("", String("".to_string()))));
+        assert_eq!(value("\"x"), Err(syntax("\"x")));
+        assert_eq!(value("x"), Err(syntax("x")));
+    }
+    #[test]
+    fn string_escape() {
+        assert_eq!(
+            value(r#""a\"b\\c\/d""#),
+            Ok(("", String("a\"b\\c/d".to_string())))
+        );
+        assert_eq!(
+            value(r#""\b\f\n\r\t""#),
+            Ok(("", String("\u{08}\u{0c}\n\r\t".to_string())))
+        );
+        assert_eq!(
+            value(r#""\u3042\u00e9\ud83d\ude00""#),
+            Ok(("", String("あé😀".to_string())))
+        );
+        assert!(value(r#""\x""#).is_err());
+        assert!(value(r#""\u12""#).is_err());
+        assert!(value(r#""\ud83d""#).is_err());
+        assert!(value(r#""\ude00""#).is_err());
+    }
+    #[test]
+    fn unescape_and_escape() {
+        let raw = "say \"hi\" \\ は😀\n\t\u{1}/";
+        let escaped = r#"say \"hi\" \\ は😀\n\t\u0001/"#;
+
+        assert_eq!(crate::escape(raw), escaped);
+        assert_eq!(crate::unescape(escaped), Ok(raw.to_string()));
+        assert_eq!(crate::unescape(&crate::escape(raw)), Ok(raw.to_string()));
+        assert_eq!(
+            crate::unescape(r#"\u3042\ud83d\ude00\/\b\f\r\u007f"#),
+            Ok("あ😀/\u{8}\u{c}\r\u{7f}".to_string())
+        );
+        assert_eq!(crate::unescape(""), Ok("".to_string()));
+        assert_eq!(crate::unescape(r#"ab\x"#), Err(Error::ParseError(3)));
+        assert_eq!(crate::unescape(r#"a"b"#), Err(Error::ParseError(1)));
+        assert_eq!(crate::unescape(r#"\ud800"#), Err(Error::ParseError(6)));
+        assert_eq!(crate::unescape("\\"), Err(Error::ParseError(1)));
+    }
+    #[test]
+    fn solidus_round_trip() {
+        let root = parse(r#"["a\/b", "c/d"]"#).unwrap();
+        assert_eq!(
+            root,
+            Root::Array(vec![String("a/b".to_string()), String("c/d".to_string())])
+        );
+
+        let json = root.to_json_string();
+        assert_eq!(json, r#"["a/b","c/d"]"#);
+        assert_eq!(parse(&json), Ok(root));
+    }
+    #[test]
+    fn array() {
+        assert_eq!(
+            value("[\"string\"]"),
+            Ok(("", Array(vec![String("string".to_string())])))
+        );
+        assert_eq!(value("[true]"), Ok(("", Array(vec![Boolean(true)]))));
+        assert_eq!(value("[1]"), Ok(("", Array(vec![Number(1.0)]))));
+        assert_eq!(value("[]"), Ok(("", Array(vec![]))));
+        assert_eq!(value("[[]]"), Ok(("", Array(vec![Array(vec![])]))));
+        assert_eq!(
+            value("[true,false]"),
+            Ok(("", Array(vec![Boolean(true), Boolean(false)])))
+        );
+        assert_eq!(value(" [true]"), Ok(("", Array(vec![Boolean(true)]))));
+        assert_eq!(value("[ true]"), Ok(("", Array(vec![Boolean(true)]))));
+        assert_eq!(value("[true ]"), Ok(("", Array(vec![Boolean(true)]))));
+        assert_eq!(value("[true] "), Ok((" ", Array(vec![Boolean(true)]))));
+    }
+    #[test]
+    fn object() {
+        assert_eq!(
+            value("{\"x\":\"y\"}"),
+            Ok((
+                "",
+                Object(hash![("x".to_string(), String("y".to_string()))])
+            ))
+        );
+        assert_eq!(
+            value("{\"x\":\"y\",\"z\":\"w\"}"),
+            Ok((
                 "",
                 Object(hash![
                     ("x".to_string(), String("y".to_string())),
@@ -276,7 +1534,7 @@ mod tests {
             ))
         );
         assert_eq!(
-            parse_value("{\"a\":{\"b\":\"c\"}}"),
+            value("{\"a\":{\"b\":\"c\"}}"),
             Ok((
                 "",
                 Object(hash![(
@@ -299,8 +1557,764 @@ mod tests {
                 Object(hash![("b".to_string(), String("c".to_string()))])
             )]))
         );
-        assert_eq!(parse("[true,false]1"), Err(Error::FoundUnTerminatedError));
-        assert_eq!(parse("[true,falsex"), Err(Error::ParseError));
+        assert_eq!(
+            parse("[true,false]1"),
+            Err(Error::FoundUnTerminatedError(12))
+        );
+        assert_eq!(parse("{} \n  x"), Err(Error::FoundUnTerminatedError(6)));
+        assert_eq!(parse("[true,falsex"), Err(Error::ParseError(11)));
+    }
+    #[test]
+    fn detect_encoding() {
+        let want = parse(r#"{"k": ["は", 1]}"#);
+        let text = "{\"k\": [\"は\", 1]}";
+        let utf16 = |bom: [u8; 2], unit: fn(u16) -> [u8; 2]| {
+            let mut bytes = bom.to_vec();
+            bytes.extend(text.encode_utf16().flat_map(unit));
+            bytes
+        };
+
+        assert_eq!(parse_detect_encoding(text.as_bytes()), want);
+        assert_eq!(
+            parse_detect_encoding(&[b"\xef\xbb\xbf", text.as_bytes()].concat()),
+            want
+        );
+        let le = utf16([0xff, 0xfe], u16::to_le_bytes);
+        assert_eq!(parse_detect_encoding(&le), want);
+        assert_eq!(
+            parse_detect_encoding(&utf16([0xfe, 0xff], u16::to_be_bytes)),
+            want
+        );
+
+        assert_eq!(
+            parse_detect_encoding(&le[..le.len() - 1]),
+            Err(Error::InvalidEncoding)
+        );
+        assert_eq!(
+            parse_detect_encoding(&[0xff, 0xfe, 0x00, 0xd8]),
+            Err(Error::InvalidEncoding)
+        );
+        assert_eq!(
+            parse_detect_encoding(b"[\xff]"),
+            Err(Error::InvalidEncoding)
+        );
+        assert_eq!(parse_detect_encoding(b"[1,]"), Err(Error::ParseError(3)));
+    }
+    #[test]
+    fn prefix() {
+        assert_eq!(
+            parse_prefix("{\"a\":1} rest"),
+            Ok((Root::Object(hash![("a".to_string(), Number(1.0))]), " rest"))
+        );
+        assert_eq!(
+            parse_prefix("[1][2]"),
+            Ok((Root::Array(vec![Number(1.0)]), "[2]"))
+        );
+        assert_eq!(parse_prefix(" [] "), Ok((Root::Array(vec![]), " ")));
+        assert_eq!(parse_prefix("[1,"), Err(Error::UnclosedContainer(0)));
+    }
+    #[test]
+    fn outcome() {
+        let input = "{} trailing";
+        let got = parse_outcome(input).unwrap();
+
+        assert_eq!(got.root, Root::Object(crate::json::Object::new()));
+        assert_eq!(got.consumed, 2);
+        assert_eq!(&input[..got.consumed], "{}");
+        assert_eq!(got.trailing, Some(" trailing".to_string()));
+
+        let got = parse_outcome(" [1] \n").unwrap();
+        assert_eq!(got.consumed, 4);
+        assert_eq!(got.trailing, None);
+
+        assert_eq!(parse_outcome("[1,"), Err(Error::UnclosedContainer(0)));
+    }
+    #[test]
+    fn array_stream() {
+        let got: Vec<_> = parse_array_stream(r#" [1, "a", {"b": null}] "#)
+            .unwrap()
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                Ok(Value::Number(1.0)),
+                Ok(Value::String("a".to_string())),
+                Ok(Value::Object(hash![("b".to_string(), Value::Null)])),
+            ]
+        );
+
+        assert_eq!(parse_array_stream("[]").unwrap().count(), 0);
+        assert!(parse_array_stream("{}").is_err());
+        assert_eq!(
+            parse_array_stream("[1, x]").unwrap().collect::<Vec<_>>(),
+            vec![Ok(Value::Number(1.0)), Err(Error::ParseError(4))]
+        );
+        assert_eq!(
+            parse_array_stream("[1] 2").unwrap().collect::<Vec<_>>(),
+            vec![
+                Ok(Value::Number(1.0)),
+                Err(Error::FoundUnTerminatedError(4))
+            ]
+        );
+    }
+    #[test]
+    fn unclosed_container() {
+        assert_eq!(parse("{\"a\":1"), Err(Error::UnclosedContainer(0)));
+        assert_eq!(parse("[1,2"), Err(Error::UnclosedContainer(0)));
+        assert_eq!(parse("  [1,2,  "), Err(Error::UnclosedContainer(2)));
+        assert_eq!(parse("{\"a\":"), Err(Error::UnclosedContainer(0)));
+        assert_eq!(parse("[[1], {\"a\": [2"), Err(Error::UnclosedContainer(12)));
+        assert_eq!(parse("[1, x"), Err(Error::ParseError(4)));
+        assert_eq!(
+            parse("{\"a\":1").unwrap_err().to_string(),
+            "Container at byte 0 is never closed"
+        );
+        assert_eq!(
+            parse_array_stream("[1, 2").unwrap().collect::<Vec<_>>(),
+            vec![
+                Ok(Value::Number(1.0)),
+                Ok(Value::Number(2.0)),
+                Err(Error::UnclosedContainer(0))
+            ]
+        );
+    }
+    #[test]
+    fn colons() {
+        assert_eq!(parse(r#"{"a" 1}"#), Err(Error::MissingColon(5)));
+        assert_eq!(parse(r#"{"a": 1, "b"}"#), Err(Error::MissingColon(12)));
+        assert_eq!(parse(r#"[{"a"}]"#), Err(Error::MissingColon(5)));
+        assert_eq!(parse("[1 : 2]"), Err(Error::UnexpectedColon(3)));
+        assert_eq!(parse("[1, :]"), Err(Error::UnexpectedColon(4)));
+        assert_eq!(parse(r#"{"a": [1: 2]}"#), Err(Error::UnexpectedColon(8)));
+        assert_eq!(parse(r#"{"a"#), Err(Error::UnclosedContainer(0)));
+        assert_eq!(
+            parse(r#"{"a" 1}"#).unwrap_err().to_string(),
+            "Expected `:` after key at byte 5"
+        );
+        assert_eq!(
+            parse("[1 : 2]").unwrap_err().to_string(),
+            "Found `:` in array at byte 3"
+        );
+    }
+    #[test]
+    fn require_root() {
+        let require = |kind| ParseOptions {
+            require_root: kind,
+            ..ParseOptions::default()
+        };
+        let object = r#" {"a": [1]}"#;
+        let array = " [{}]";
+
+        for &kind in &[RootKind::Any, RootKind::Object] {
+            assert_eq!(parse_with_options(object, &require(kind)), parse(object));
+        }
+        for &kind in &[RootKind::Any, RootKind::Array] {
+            assert_eq!(parse_with_options(array, &require(kind)), parse(array));
+        }
+        assert_eq!(
+            parse_with_options(array, &require(RootKind::Object)),
+            Err(Error::UnexpectedRootKind(1))
+        );
+        assert_eq!(
+            parse_with_options(object, &require(RootKind::Array)),
+            Err(Error::UnexpectedRootKind(1))
+        );
+        assert_eq!(
+            parse_with_options("1", &require(RootKind::Object)),
+            Err(Error::ParseError(0))
+        );
+
+        let mut root = Root::Array(vec![]);
+        assert_eq!(
+            Parser::new(&require(RootKind::Object)).parse_into(array, &mut root),
+            Err(Error::UnexpectedRootKind(1))
+        );
+    }
+    #[test]
+    fn from_str() {
+        assert_eq!(
+            "[1, true]".parse::<Root>(),
+            Ok(Root::Array(vec![Number(1.0), Boolean(true)]))
+        );
+        assert_eq!(
+            "[1] x".parse::<Root>(),
+            Err(Error::FoundUnTerminatedError(4))
+        );
+        assert_eq!("[1,]".parse::<Root>(), Err(Error::ParseError(3)));
+    }
+
+    #[test]
+    fn error_match() {
+        let describe = |input| match parse(input) {
+            Ok(_) => "ok".to_string(),
+            Err(Error::UnclosedContainer(p)) => format!("close the container at {}", p),
+            Err(Error::MissingColon(p)) => format!("add `:` at {}", p),
+            Err(e) => match e.position() {
+                Some(p) => format!("fix byte {}", p),
+                None => e.to_string(),
+            },
+        };
+
+        assert_eq!(describe("[]"), "ok");
+        assert_eq!(describe("[1, [2"), "close the container at 4");
+        assert_eq!(describe("{\"a\" 1}"), "add `:` at 5");
+        assert_eq!(describe("[1,]"), "fix byte 3");
+    }
+
+    #[test]
+    fn error_position() {
+        assert_eq!(parse("x"), Err(Error::ParseError(0)));
+        assert_eq!(parse("  [1, x]"), Err(Error::ParseError(6)));
+        assert_eq!(parse("[1,]"), Err(Error::ParseError(3)));
+        assert_eq!(parse("{\"a\":1,}"), Err(Error::ParseError(7)));
+        assert_eq!(parse("{\"a\" 1}"), Err(Error::MissingColon(5)));
+        assert_eq!(parse("{\"a\": {\"b\": ?}}"), Err(Error::ParseError(12)));
+        assert_eq!(
+            parse("[1 , 2 ]"),
+            Ok(Root::Array(vec![Number(1.0), Number(2.0)]))
+        );
+    }
+    #[test]
+    fn error_position_after_multi_byte_key() {
+        let input = "{\"はろー\": x}";
+        let e = parse(input).unwrap_err();
+
+        assert_eq!(e, Error::ParseError(14));
+        assert_eq!(&input[e.position().unwrap()..], "x}");
+        assert_eq!(char_index(input, e.position().unwrap()), 8);
+    }
+    #[test]
+    fn report() {
+        let input = "{\n  \"a\": 1,\n  \"は\": ?\n}";
+        let e = parse(input).unwrap_err();
+
+        assert_eq!(e, Error::ParseError(21));
+        assert_eq!(line_column(input, 21), (3, 8));
+        assert_eq!(
+            e.report(input).to_string(),
+            "error at line 3, column 8: failed to parse value."
+        );
+        assert_eq!(
+            e.report(input).file("config.json").to_string(),
+            "config.json:3:8: failed to parse value"
+        );
+        assert_eq!(
+            Error::InputTooLarge.report(input).to_string(),
+            "error: input is too large."
+        );
+        assert_eq!(line_column("ab", 0), (1, 1));
+        assert_eq!(line_column("a\n", 2), (2, 1));
+    }
+    #[test]
+    fn char_index_of_byte_offset() {
+        assert_eq!(char_index("abc", 0), 0);
+        assert_eq!(char_index("abc", 2), 2);
+        assert_eq!(char_index("はろー", 3), 1);
+        assert_eq!(char_index("はろー", 4), 1);
+        assert_eq!(char_index("はろー", 9), 3);
+    }
+    #[test]
+    fn string_heavy() {
+        let long = "x".repeat(4096);
+        let input = format!(
+            "[{}]",
+            (0..256)
+                .map(|i| format!("\"{}\\n{}\"", long, i))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+
+        match parse(&input) {
+            Ok(Root::Array(a)) => {
+                assert_eq!(a.len(), 256);
+                for (i, v) in a.iter().enumerate() {
+                    assert_eq!(v, &String(format!("{}\n{}", long, i)));
+                }
+            }
+            got => panic!("Want Array, got {:?}", got),
+        }
+    }
+    mod allocations {
+        use core::cell::Cell;
+        use std::alloc::{GlobalAlloc, Layout, System};
+
+        struct Counter;
+
+        thread_local! {
+            static COUNT: Cell<usize> = const { Cell::new(0) };
+        }
+
+        fn bump() {
+            let _ = COUNT.try_with(|c| c.set(c.get() + 1));
+        }
+
+        unsafe impl GlobalAlloc for Counter {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                bump();
+                System.alloc(layout)
+            }
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+            unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+                bump();
+                System.realloc(ptr, layout, new_size)
+            }
+        }
+
+        #[global_allocator]
+        static GLOBAL: Counter = Counter;
+
+        /// Number of allocations and reallocations made by this thread while running `f`.
+        pub fn count<T>(f: impl FnOnce() -> T) -> usize {
+            let before = COUNT.with(|c| c.get());
+            let x = f();
+            let after = COUNT.with(|c| c.get());
+            drop(x);
+            after - before
+        }
+    }
+    #[test]
+    fn capacity_hint() {
+        let nulls = vec!["null"; 10000].join(",");
+        let root = format!("[{}]", nulls);
+        let nested = format!("[[{}]]", nulls);
+
+        // The nested array has no hint and grows as it fills.
+        let hinted = allocations::count(|| parse(&root).unwrap());
+        let grown = allocations::count(|| parse(&nested).unwrap());
+        assert!(hinted * 4 < grown, "{} vs {}", hinted, grown);
+
+        let object = format!(
+            "{{{}}}",
+            (0..100)
+                .map(|i| format!("\"{}\":null", i))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        // One allocation per key, plus a few for the member list and the map,
+        // instead of one per doubling. A `BTreeMap` allocates per node instead.
+        if !cfg!(feature = "btree_map") {
+            let count = allocations::count(|| parse(&object).unwrap());
+            assert!(count <= 100 + 4, "{}", count);
+        }
+    }
+    #[test]
+    fn parser_reuse() {
+        let mut parser = Parser::default();
+        let mut root = Root::Array(vec![]);
+
+        let array = "[true, null, false, [null]]";
+        let fresh = allocations::count(|| parse(array).unwrap());
+        parser.parse_into(array, &mut root).unwrap();
+        // Only the nested array needs a fresh allocation.
+        let reused = allocations::count(|| parser.parse_into(array, &mut root).unwrap());
+        assert!(reused < fresh && reused <= 1, "{} vs {}", reused, fresh);
+        assert_eq!(Ok(root.clone()), parse(array));
+
+        let object = r#"{"a": null, "b": true}"#;
+        let fresh = allocations::count(|| parse(object).unwrap());
+        // The first parse replaces the array root, the second sizes the member buffer.
+        for _ in 0..2 {
+            parser.parse_into(object, &mut root).unwrap();
+        }
+        // Just the two keys, plus a node for a `BTreeMap`.
+        let reused = allocations::count(|| parser.parse_into(object, &mut root).unwrap());
+        assert!(reused < fresh && reused <= 3, "{} vs {}", reused, fresh);
+        assert_eq!(Ok(root.clone()), parse(object));
+
+        assert_eq!(
+            parser.parse_into("[1] x", &mut root),
+            Err(Error::FoundUnTerminatedError(4))
+        );
+        assert_eq!(
+            parser.parse_into(" [1,", &mut root),
+            Err(Error::UnclosedContainer(1))
+        );
+        assert_eq!(parser.parse_into("x", &mut root), Err(Error::ParseError(0)));
+    }
+    #[test]
+    fn parse_value_into() {
+        let mut value = Value::Null;
+
+        let first = r#"{"a": [1, 2], "b": "x"}"#;
+        parse_into(first, &mut value).unwrap();
+        assert_eq!(Ok(value.clone().try_into_root().unwrap()), parse(first));
+
+        let second = r#"{"c": null}"#;
+        let mut parser = Parser::default();
+        parser.parse_value_into(second, &mut value).unwrap();
+        assert_eq!(Ok(value.clone().try_into_root().unwrap()), parse(second));
+        // Both the object and the member buffer are sized by now, so only the
+        // key is allocated, plus a node for a `BTreeMap`.
+        let fresh = allocations::count(|| parse(second).unwrap());
+        let reused = allocations::count(|| parser.parse_value_into(second, &mut value).unwrap());
+        assert!(reused < fresh && reused <= 2, "{} vs {}", reused, fresh);
+
+        parse_into("[true]", &mut value).unwrap();
+        assert_eq!(value, Array(vec![Boolean(true)]));
+        assert_eq!(
+            parse_into("[1,", &mut value),
+            Err(Error::UnclosedContainer(0))
+        );
+        assert_eq!(parse_into("1", &mut value), Err(Error::ParseError(0)));
+    }
+    #[test]
+    fn intern_keys() {
+        let records = 100;
+        let keys = 4;
+        let record = r#"{"a": true, "b": null, "c": false, "d": null}"#;
+        let document = format!("[{}]", vec![record; records].join(","));
+        let parse_all = |parser: &mut Parser| {
+            let mut root = Root::Array(vec![]);
+            let count = allocations::count(|| parser.parse_into(&document, &mut root).unwrap());
+            assert_eq!(Ok(root), parse(&document));
+            count
+        };
+        // The records and the array allocate as much either way, so the
+        // difference is the keys, which interning allocates once each, plus
+        // the one node of the cache.
+        let plain = parse_all(&mut Parser::default());
+        let mut parser = Parser::default().intern_keys();
+        let interned = parse_all(&mut parser);
+        assert!(
+            interned + (records - 1) * keys <= plain + 1,
+            "{} vs {}",
+            interned,
+            plain
+        );
+        // The keys stay cached while a parsed tree holds them, so the next
+        // document does not allocate them again.
+        let parse_next = |parser: &mut Parser| {
+            let mut root = parse(record).unwrap();
+            parser.parse_into(record, &mut root).unwrap();
+            allocations::count(|| parser.parse_into(record, &mut root).unwrap())
+        };
+        let plain = parse_next(&mut Parser::default());
+        let interned = parse_next(&mut parser);
+        assert!(interned + keys <= plain, "{} vs {}", interned, plain);
+
+        // Keys of nested objects are reused too, and escaped keys still decode.
+        let mut parser = Parser::default().intern_keys();
+        let mut root = Root::Array(vec![]);
+        let nested = r#"[{"a": {"b": [{"a": 1}]}}, {"\u0061": 2, "a\"": 3}]"#;
+        for _ in 0..3 {
+            parser.parse_into(nested, &mut root).unwrap();
+            assert_eq!(Ok(root.clone()), parse(nested));
+        }
+
+        // A cached key does not get around the checks on strings.
+        let mut parser = Parser::new(&ParseOptions::strict()).intern_keys();
+        let mut root = Root::Object(vec![("a\u{1}".into(), Null)].into_iter().collect());
+        assert_eq!(
+            parser.parse_into("{\"a\u{1}\": 1}", &mut root),
+            Err(Error::StrictViolation(3, "control character in string"))
+        );
+    }
+    #[test]
+    fn max_depth() {
+        let limited = |max| ParseOptions {
+            max_depth: Some(max),
+            ..ParseOptions::default()
+        };
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+
+        assert_eq!(
+            parse_with_options(&deep, &limited(128)),
+            Err(Error::DepthLimitExceeded(128))
+        );
+        assert_eq!(
+            parse_with_options(r#"{"a": [{"b": []}]}"#, &limited(3)),
+            Err(Error::DepthLimitExceeded(13))
+        );
+        assert_eq!(
+            parse_with_options(r#"{"a": [{"b": [], "c": 1}]}"#, &limited(4)),
+            parse(r#"{"a": [{"b": [], "c": 1}]}"#)
+        );
+        assert_eq!(
+            parse_with_options("[]", &limited(0)),
+            Err(Error::DepthLimitExceeded(0))
+        );
+        assert_eq!(
+            Parser::new(&limited(1)).parse_into("[[]]", &mut Root::Array(vec![])),
+            Err(Error::DepthLimitExceeded(1))
+        );
+        assert_eq!(
+            Error::DepthLimitExceeded(13).to_string(),
+            "Nesting is too deep at byte 13"
+        );
+    }
+    #[test]
+    fn max_string_len() {
+        let options = ParseOptions {
+            max_string_len: Some(4),
+            ..ParseOptions::default()
+        };
+
+        assert_eq!(
+            parse_with_options(r#"["abcd", "\u3042"]"#, &options),
+            parse(r#"["abcd", "\u3042"]"#)
+        );
+        let huge = format!("[1, \"{}\"]", "x".repeat(1 << 20));
+        assert_eq!(
+            parse_with_options(&huge, &options),
+            Err(Error::StringTooLong(4))
+        );
+        assert_eq!(
+            parse_with_options(r#"{"a": "\n\n\n\n\n"}"#, &options),
+            Err(Error::StringTooLong(6))
+        );
+        assert_eq!(
+            parse_with_options(r#"{"a": 1, "abcde": 2}"#, &options),
+            Err(Error::StringTooLong(9))
+        );
+        assert_eq!(parse(&huge).map(|_| ()), Ok(()));
+    }
+    #[test]
+    fn relaxed() {
+        let json5 = r#"{
+            // JSON5-style
+            name: 'rson',
+            "quote": 'say "hi" and \'bye\'',
+            $list_2: [1, 2, 3,],
+            nested: {a: 1, /* last */},
+        }"#;
+
+        assert_eq!(
+            parse_with_options(json5, &ParseOptions::relaxed()),
+            Ok(Root::Object(hash![
+                ("name".to_string(), String("rson".to_string())),
+                (
+                    "quote".to_string(),
+                    String("say \"hi\" and 'bye'".to_string())
+                ),
+                (
+                    "$list_2".to_string(),
+                    Array(vec![Number(1.0), Number(2.0), Number(3.0)])
+                ),
+                (
+                    "nested".to_string(),
+                    Object(hash![("a".to_string(), Number(1.0))])
+                )
+            ]))
+        );
+        assert!(parse_with_options(json5, &ParseOptions::strict()).is_err());
+        assert!(parse(json5).is_err());
+
+        let relaxed = ParseOptions::relaxed();
+        assert_eq!(
+            parse_with_options("[1,,]", &relaxed),
+            Err(Error::ParseError(3))
+        );
+        assert_eq!(
+            parse_with_options("[,]", &relaxed),
+            Err(Error::ParseError(1))
+        );
+        assert_eq!(
+            parse_with_options("{1a: 1}", &relaxed),
+            Err(Error::ParseError(1))
+        );
+        assert_eq!(
+            parse_with_options("['a\"]", &relaxed),
+            Err(Error::ParseError(1))
+        );
+        assert_eq!(
+            parse_with_options("[+1]", &ParseOptions::strict()),
+            Err(Error::ParseError(1))
+        );
+    }
+    #[test]
+    fn strict_rfc_8259() {
+        let strict = ParseOptions::strict();
+        assert_eq!(
+            parse_with_options("[1, 01]", &strict),
+            Err(Error::StrictViolation(4, "leading zero"))
+        );
+        assert_eq!(
+            parse_with_options("[-00.5]", &strict),
+            Err(Error::StrictViolation(1, "leading zero"))
+        );
+        assert_eq!(
+            parse_with_options("[0, -0, 0.5, 0e1]", &strict),
+            parse("[0, -0, 0.5, 0e1]")
+        );
+        assert_eq!(
+            parse_with_options("[\"a\\n\tb\"]", &strict),
+            Err(Error::StrictViolation(5, "control character in string"))
+        );
+        assert_eq!(
+            parse_with_options(r#"["a\nb\u0000"]"#, &strict),
+            parse(r#"["a\nb\u0000"]"#)
+        );
+        assert_eq!(
+            parse_with_options(r#"{"a": {"b": 1, "c": 2, "b": 3}}"#, &strict),
+            Err(Error::StrictViolation(23, "duplicate key"))
+        );
+        assert_eq!(
+            parse_with_options(r#"{"a": 1, "a": 2}"#, &strict),
+            Err(Error::StrictViolation(9, "duplicate key"))
+        );
+
+        let mut root = Root::Array(vec![]);
+        assert_eq!(
+            Parser::new(&strict).parse_into(r#"{"a": 1, "a": 2}"#, &mut root),
+            Err(Error::StrictViolation(9, "duplicate key"))
+        );
+    }
+    #[test]
+    fn duplicate_keys() {
+        let merge = ParseOptions {
+            duplicate_keys: DuplicateKeys::MergeObjects,
+            ..ParseOptions::default()
+        };
+        let input = r#"{"a":{"x":1},"a":{"y":2}}"#;
+        let want = Root::Object(hash![(
+            "a".to_string(),
+            Object(hash![
+                ("x".to_string(), Number(1.0)),
+                ("y".to_string(), Number(2.0))
+            ])
+        )]);
+        assert_eq!(parse_with_options(input, &merge), Ok(want.clone()));
+
+        let mut root = Root::Object(crate::json::Object::new());
+        Parser::new(&merge).parse_into(input, &mut root).unwrap();
+        assert_eq!(root, want);
+
+        assert_eq!(
+            parse(input),
+            Ok(Root::Object(hash![(
+                "a".to_string(),
+                Object(hash![("y".to_string(), Number(2.0))])
+            )]))
+        );
+        assert_eq!(
+            parse_with_options(
+                r#"{"a":{"b":{"x":1}},"a":{"b":{"y":2},"c":3},"d":1,"d":{}}"#,
+                &merge
+            ),
+            parse(r#"{"a":{"b":{"x":1,"y":2},"c":3},"d":{}}"#)
+        );
+        assert_eq!(
+            parse_with_options(r#"{"a":{"x":1},"a":2}"#, &merge),
+            parse(r#"{"a":2}"#)
+        );
     }
+    #[test]
+    fn reject_nul_in_keys() {
+        let options = ParseOptions {
+            reject_nul_in_keys: true,
+            ..ParseOptions::default()
+        };
+        let input = r#"{"a": {"b\u0000c": 1}}"#;
 
+        assert_eq!(
+            parse_with_options(input, &options),
+            Err(Error::StrictViolation(7, "NUL in key"))
+        );
+        assert_eq!(
+            parse(input),
+            Ok(Root::Object(hash![(
+                "a".to_string(),
+                Object(hash![("b\u{0}c".to_string(), Number(1.0))])
+            )]))
+        );
+        assert!(parse_with_options(r#"{"a": "\u0000"}"#, &options).is_ok());
+    }
+    #[test]
+    fn hex_escapes() {
+        let relaxed = ParseOptions::relaxed();
+        assert_eq!(
+            parse_with_options(r#"["\x41", '\xe9\x7E']"#, &relaxed),
+            Ok(Root::Array(vec![
+                String("A".to_string()),
+                String("\u{e9}~".to_string())
+            ]))
+        );
+        assert!(parse_with_options(r#"["\x4"]"#, &relaxed).is_err());
+        assert!(parse_with_options(r#"["\xG1"]"#, &relaxed).is_err());
+        assert_eq!(
+            parse_with_options(r#"["\x41"]"#, &ParseOptions::strict()),
+            Err(Error::ParseError(1))
+        );
+        assert_eq!(parse(r#"["\x41"]"#), Err(Error::ParseError(1)));
+    }
+    #[test]
+    fn undefined_as_null() {
+        let lenient = ParseOptions {
+            undefined_as_null: true,
+            ..ParseOptions::relaxed()
+        };
+        assert_eq!(
+            parse_with_options("[undefined, {a: undefined}]", &lenient),
+            Ok(Root::Array(vec![
+                Null,
+                Object(hash![("a".to_string(), Null)])
+            ]))
+        );
+        assert_eq!(
+            parse_with_options("[undefined]", &ParseOptions::strict()),
+            Err(Error::ParseError(1))
+        );
+        let strict = ParseOptions {
+            strict: true,
+            ..lenient
+        };
+        assert_eq!(
+            parse_with_options("[undefined]", &strict),
+            Err(Error::ParseError(1))
+        );
+        assert_eq!(parse("[undefined]"), Err(Error::ParseError(1)));
+    }
+    #[test]
+    fn byte_order_mark() {
+        let input = "{\"a\": [1,\u{feff} 2]}";
+        for options in &[
+            ParseOptions::strict(),
+            ParseOptions::default(),
+            ParseOptions::relaxed(),
+        ] {
+            assert_eq!(
+                parse_with_options(input, options),
+                Err(Error::UnexpectedBom(9))
+            );
+        }
+        assert_eq!(parse("\u{feff}[]"), Err(Error::UnexpectedBom(0)));
+        assert_eq!(parse("[] \u{feff}"), Err(Error::UnexpectedBom(3)));
+        assert_eq!(parse("{\"a\"\u{feff}: 1}"), Err(Error::UnexpectedBom(4)));
+        assert!(parse("[\"\u{feff}\"]").is_ok());
+
+        let mut root = Root::Array(vec![]);
+        assert_eq!(
+            Parser::new(&ParseOptions::default()).parse_into("[1]\u{feff}", &mut root),
+            Err(Error::UnexpectedBom(3))
+        );
+        assert_eq!(
+            parse_detect_encoding("\u{feff}[\u{feff}]".as_bytes()),
+            Err(Error::UnexpectedBom(1))
+        );
+    }
+    #[test]
+    fn max_total_bytes() {
+        let options = ParseOptions {
+            max_total_bytes: Some(8),
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            parse_with_options("[1,2,3]", &options),
+            Ok(Root::Array(vec![Number(1.0), Number(2.0), Number(3.0)]))
+        );
+        assert_eq!(
+            parse_with_options("[1,2,3,4]", &options),
+            Err(Error::InputTooLarge)
+        );
+        assert_eq!(
+            parse_with_options("[1,2,3,", &options),
+            Err(Error::UnclosedContainer(0))
+        );
+        assert_eq!(
+            parse_with_options("[1, 2, 3, 4, 5, 6, 7, 8, 9", &options),
+            Err(Error::InputTooLarge)
+        );
+    }
 }
@@ -0,0 +1,260 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::combinator::{parse_comment, parse_with_options, Error, ParseOptions};
+use crate::json::{push_pointer_token, Root};
+use crate::lexer::{Lexer, Token};
+
+/// Comments of a document, attached to values by their JSON Pointer.
+///
+/// A comment on the same line after a value (or after the comma following it)
+/// trails that value; any other comment leads the next value. Comments with no
+/// following value trail the last value before them, or the enclosing
+/// container when it is empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comments {
+    leading: BTreeMap<String, Vec<String>>,
+    trailing: BTreeMap<String, Vec<String>>,
+}
+
+impl Comments {
+    /// Comments written before the value at `pointer`, or before its key for object members.
+    pub fn leading(&self, pointer: &str) -> &[String] {
+        self.leading.get(pointer).map_or(&[], Vec::as_slice)
+    }
+
+    /// Comments written after the value at `pointer`.
+    pub fn trailing(&self, pointer: &str) -> &[String] {
+        self.trailing.get(pointer).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_empty()
+    }
+}
+
+/// Parses `s` with comments enabled and keeps them, so that they can be written
+/// back with `Root::to_json_string_with_comments`.
+pub fn parse_with_comments(s: &str, options: &ParseOptions) -> Result<(Root, Comments), Error> {
+    let options = ParseOptions {
+        comments: true,
+        ..options.clone()
+    };
+    let root = parse_with_options(s, &options)?;
+    Ok((root, collect(s, &options)))
+}
+
+enum Frame {
+    Array { index: usize },
+    Object { key: Option<String> },
+}
+
+#[derive(Default)]
+struct Collector {
+    comments: Comments,
+    // Pointer of the innermost open container.
+    path: String,
+    stack: Vec<(Frame, usize)>,
+    last_value: Option<String>,
+    after_value: bool,
+    pending: Vec<String>,
+}
+
+fn collect(s: &str, options: &ParseOptions) -> Comments {
+    let mut c = Collector::default();
+    let mut end = 0;
+    for (token, span) in Lexer::with_options(s, options).spanned() {
+        c.gap(&s[end..span.start]);
+        c.token(token);
+        end = span.end;
+    }
+    c.gap(&s[end..]);
+    c.finish()
+}
+
+impl Collector {
+    fn gap(&mut self, gap: &str) {
+        let mut offset = 0;
+        while let Some(c) = gap[offset..].chars().next() {
+            match parse_comment(&gap[offset..]) {
+                Ok((rest, comment)) => {
+                    let same_line = !gap[..offset].contains('\n');
+                    if same_line && self.after_value && self.last_value.is_some() {
+                        let pointer = self.last_value.clone().unwrap_or_default();
+                        push(&mut self.comments.trailing, pointer, comment.to_string());
+                    } else {
+                        self.pending.push(comment.to_string());
+                    }
+                    offset = gap.len() - rest.len();
+                }
+                Err(_) => offset += c.len_utf8(),
+            }
+        }
+    }
+
+    fn token(&mut self, token: Token) {
+        match token {
+            Token::String(key) if self.expects_key() => {
+                let mut pointer = self.path.clone();
+                push_pointer_token(&mut pointer, &key);
+                self.flush_leading(&pointer);
+                if let Some((Frame::Object { key: k }, _)) = self.stack.last_mut() {
+                    *k = Some(key);
+                }
+                self.after_value = false;
+            }
+            Token::LeftBrace | Token::LeftBracket => {
+                let pointer = self.value_pointer();
+                self.flush_leading(&pointer);
+                let frame = match token {
+                    Token::LeftBrace => Frame::Object { key: None },
+                    _ => Frame::Array { index: 0 },
+                };
+                self.stack.push((frame, self.path.len()));
+                self.path = pointer;
+                self.last_value = None;
+                self.after_value = false;
+            }
+            Token::RightBrace | Token::RightBracket => {
+                self.flush_trailing();
+                let pointer = self.path.clone();
+                if let Some((_, len)) = self.stack.pop() {
+                    self.path.truncate(len);
+                }
+                self.complete_value(pointer);
+            }
+            Token::String(_) | Token::Number(_) | Token::Boolean(_) | Token::Null => {
+                let pointer = self.value_pointer();
+                self.flush_leading(&pointer);
+                self.complete_value(pointer);
+            }
+            Token::Comma => self.flush_trailing(),
+            Token::Colon => self.after_value = false,
+            Token::Whitespace(_) | Token::Comment(_) | Token::Illegal(..) => {}
+        }
+    }
+
+    fn finish(mut self) -> Comments {
+        self.flush_trailing();
+        self.comments
+    }
+
+    fn expects_key(&self) -> bool {
+        match self.stack.last() {
+            Some((Frame::Object { key }, _)) => key.is_none(),
+            _ => false,
+        }
+    }
+
+    fn value_pointer(&self) -> String {
+        let mut pointer = self.path.clone();
+        match self.stack.last() {
+            Some((Frame::Array { index }, _)) => {
+                push_pointer_token(&mut pointer, &index.to_string())
+            }
+            Some((Frame::Object { key: Some(key) }, _)) => push_pointer_token(&mut pointer, key),
+            _ => {}
+        }
+        pointer
+    }
+
+    fn complete_value(&mut self, pointer: String) {
+        match self.stack.last_mut() {
+            Some((Frame::Array { index }, _)) => *index += 1,
+            Some((Frame::Object { key }, _)) => *key = None,
+            None => {}
+        }
+        self.last_value = Some(pointer);
+        self.after_value = true;
+    }
+
+    fn flush_leading(&mut self, pointer: &str) {
+        if !self.pending.is_empty() {
+            let pending = core::mem::take(&mut self.pending);
+            for comment in pending {
+                push(&mut self.comments.leading, pointer.to_string(), comment);
+            }
+        }
+    }
+
+    fn flush_trailing(&mut self) {
+        if !self.pending.is_empty() {
+            let pointer = self.last_value.clone().unwrap_or_else(|| self.path.clone());
+            let pending = core::mem::take(&mut self.pending);
+            for comment in pending {
+                push(&mut self.comments.trailing, pointer.clone(), comment);
+            }
+        }
+    }
+}
+
+fn push(map: &mut BTreeMap<String, Vec<String>>, pointer: String, comment: String) {
+    map.entry(pointer).or_default().push(comment);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combinator::{parse_with_options, ParseOptions};
+    use crate::comments::parse_with_comments;
+
+    #[test]
+    fn attach() {
+        let input = r#"// config
+{
+    "servers": [
+        "a", // primary
+        /* backup */ "b"
+    ] /* end servers */
+}
+// eof"#;
+        let (_, comments) = parse_with_comments(input, &ParseOptions::default()).unwrap();
+
+        assert_eq!(comments.leading(""), ["// config"]);
+        assert_eq!(comments.trailing("/servers/0"), ["// primary"]);
+        assert_eq!(comments.leading("/servers/1"), ["/* backup */"]);
+        assert_eq!(comments.trailing("/servers"), ["/* end servers */"]);
+        assert_eq!(comments.trailing(""), ["// eof"]);
+        assert!(comments.leading("/servers").is_empty());
+    }
+    #[test]
+    fn member_and_empty_container() {
+        let input = "{ /* k */ \"a~/b\" /* c */ : [ /* empty */ ] }";
+        let (_, comments) = parse_with_comments(input, &ParseOptions::default()).unwrap();
+
+        assert_eq!(comments.leading("/a~0~1b"), ["/* k */", "/* c */"]);
+        assert_eq!(comments.trailing("/a~0~1b"), ["/* empty */"]);
+    }
+    #[test]
+    fn round_trip() {
+        let input = r#"// config
+{
+    "servers": [
+        "a", // primary
+        /* backup */ "b"
+    ] /* end servers */
+}
+// eof"#;
+        let (root, comments) = parse_with_comments(input, &ParseOptions::default()).unwrap();
+        let output = root.to_json_string_with_comments(&comments);
+
+        assert_eq!(
+            output,
+            "// config\n{\"servers\":[\"a\"// primary\n,\n/* backup */\"b\"]/* end servers */}// eof\n"
+        );
+
+        let options = ParseOptions {
+            comments: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(parse_with_options(&output, &options), Ok(root.clone()));
+        assert_eq!(parse_with_comments(&output, &options), Ok((root, comments)));
+    }
+    #[test]
+    fn no_comments() {
+        let (root, comments) = parse_with_comments("[1]", &ParseOptions::default()).unwrap();
+
+        assert!(comments.is_empty());
+        assert_eq!(root.to_json_string_with_comments(&comments), "[1]");
+    }
+}
@@ -0,0 +1,479 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use core::fmt;
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+use crate::json::{Object, Value};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConvError {
+    /// The value is not of the JSON type the target needs.
+    TypeMismatch { expected: &'static str },
+    /// The number is fractional or does not fit in the target type.
+    OutOfRange,
+    /// A struct field without a member, whose type does not accept `null`.
+    MissingField(&'static str),
+}
+
+impl Display for ConvError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ConvError::TypeMismatch { expected } => write!(f, "Expected {}", expected),
+            ConvError::OutOfRange => write!(f, "Number is out of range"),
+            ConvError::MissingField(name) => write!(f, "Missing field {}", name),
+        }
+    }
+}
+
+/// Typed extraction from a parsed `Value`.
+pub trait FromValue: Sized {
+    fn from_value(v: &Value) -> Result<Self, ConvError>;
+}
+
+impl FromValue for bool {
+    fn from_value(v: &Value) -> Result<Self, ConvError> {
+        match v {
+            Value::Boolean(b) => Ok(*b),
+            _ => Err(ConvError::TypeMismatch {
+                expected: "boolean",
+            }),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(v: &Value) -> Result<Self, ConvError> {
+        match v {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(ConvError::TypeMismatch { expected: "string" }),
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(v: &Value) -> Result<Self, ConvError> {
+        match v {
+            Value::Number(n) => Ok(*n),
+            _ => Err(ConvError::TypeMismatch { expected: "number" }),
+        }
+    }
+}
+
+impl FromValue for f32 {
+    fn from_value(v: &Value) -> Result<Self, ConvError> {
+        f64::from_value(v).map(|n| n as f32)
+    }
+}
+
+macro_rules! from_value_for_int {
+    ( $( $t:ident )* ) => {
+        $(
+            impl FromValue for $t {
+                fn from_value(v: &Value) -> Result<Self, ConvError> {
+                    let n = f64::from_value(v)?;
+                    // `MAX as f64` may round up to the next power of two, so the
+                    // upper bound is exclusive.
+                    if n % 1.0 == 0.0 && n >= $t::MIN as f64 && n < $t::MAX as f64 + 1.0 {
+                        Ok(n as $t)
+                    } else {
+                        Err(ConvError::OutOfRange)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_value_for_int!(i8 i16 i32 i64 isize u8 u16 u32 u64 usize);
+
+macro_rules! value_as_int {
+    ( $( $name:ident $t:ident )* ) => {
+        impl Value {
+            $(
+                /// The number as an integer of this width, or `None` if it is not
+                /// a number, has a fractional part or is out of range.
+                pub fn $name(&self) -> Option<$t> {
+                    $t::from_value(self).ok()
+                }
+            )*
+        }
+    };
+}
+
+value_as_int!(
+    as_i8 i8 as_i16 i16 as_i32 i32 as_i64 i64 as_exact_i64 i64
+    as_u8 u8 as_u16 u16 as_u32 u32 as_u64 u64
+);
+
+macro_rules! try_from_value {
+    ( $( $t:ident )* ) => {
+        $(
+            impl TryFrom<&Value> for $t {
+                type Error = ConvError;
+
+                fn try_from(v: &Value) -> Result<Self, ConvError> {
+                    $t::from_value(v)
+                }
+            }
+        )*
+    };
+}
+
+try_from_value!(String f64 i64 bool);
+
+macro_rules! try_from_owned_value {
+    ( $( $t:ident )* ) => {
+        $(
+            impl TryFrom<Value> for $t {
+                type Error = ConvError;
+
+                fn try_from(v: Value) -> Result<Self, ConvError> {
+                    $t::from_value(&v)
+                }
+            }
+        )*
+    };
+}
+
+try_from_owned_value!(f64 i64 bool);
+
+// Moves the string out instead of cloning it.
+impl TryFrom<Value> for String {
+    type Error = ConvError;
+
+    fn try_from(v: Value) -> Result<Self, ConvError> {
+        match v {
+            Value::String(s) => Ok(s),
+            _ => Err(ConvError::TypeMismatch { expected: "string" }),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(v: &Value) -> Result<Self, ConvError> {
+        match v {
+            Value::Null => Ok(None),
+            v => T::from_value(v).map(Some),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(v: &Value) -> Result<Self, ConvError> {
+        match v {
+            Value::Array(a) => a.iter().map(T::from_value).collect(),
+            _ => Err(ConvError::TypeMismatch { expected: "array" }),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(v: &Value) -> Result<Self, ConvError> {
+        match v {
+            Value::Object(o) => o
+                .iter()
+                .map(|(k, v)| T::from_value(v).map(|v| (k.to_string(), v)))
+                .collect(),
+            _ => Err(ConvError::TypeMismatch { expected: "object" }),
+        }
+    }
+}
+
+/// Implements `FromValue` for a struct with named fields, each read from the
+/// object member of the same name with its own `FromValue` impl. A missing
+/// member is read as `null`, so `Option` fields become `None` and others fail
+/// with `ConvError::MissingField`. Invoked as `from_value_struct!(Package { name, version });`.
+#[macro_export]
+macro_rules! from_value_struct {
+    ( $name:ident { $( $field:ident ),* $(,)? } ) => {
+        impl $crate::convert::FromValue for $name {
+            fn from_value(
+                v: &$crate::json::Value,
+            ) -> Result<Self, $crate::convert::ConvError> {
+                match v {
+                    $crate::json::Value::Object(o) => Ok($name {
+                        $( $field: $crate::convert::field(o, stringify!($field))?, )*
+                    }),
+                    _ => Err($crate::convert::ConvError::TypeMismatch { expected: "object" }),
+                }
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+pub fn field<T: FromValue>(o: &Object, name: &'static str) -> Result<T, ConvError> {
+    match o.get(name) {
+        Some(v) => T::from_value(v),
+        None => T::from_value(&Value::Null).map_err(|_| ConvError::MissingField(name)),
+    }
+}
+
+/// Uniform construction of a `Value` from Rust data.
+pub trait ToValue {
+    fn to_value(&self) -> Value;
+}
+
+macro_rules! to_value_via_from {
+    ( $( $t:ident )* ) => {
+        $(
+            impl ToValue for $t {
+                fn to_value(&self) -> Value {
+                    Value::from(*self)
+                }
+            }
+        )*
+    };
+}
+
+to_value_via_from!(bool f32 f64 i8 i16 i32 i64 isize u8 u16 u32 u64 usize);
+
+impl ToValue for str {
+    fn to_value(&self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl ToValue for String {
+    fn to_value(&self) -> Value {
+        Value::from(self.as_str())
+    }
+}
+
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> Value {
+        match self {
+            Some(v) => v.to_value(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<T: ToValue> ToValue for Vec<T> {
+    fn to_value(&self) -> Value {
+        Value::Array(self.iter().map(ToValue::to_value).collect())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: ToValue> ToValue for HashMap<String, T> {
+    fn to_value(&self) -> Value {
+        Value::Object(
+            self.iter()
+                .map(|(k, v)| (k.as_str().into(), v.to_value()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::{TryFrom, TryInto};
+    #[cfg(feature = "std")]
+    use std::collections::HashMap;
+
+    use crate::combinator::parse;
+    use crate::convert::{ConvError, FromValue, ToValue};
+    use crate::json::{Root, Value};
+
+    fn parse_value(s: &str) -> Value {
+        match parse(s) {
+            Ok(Root::Object(o)) => Value::Object(o),
+            Ok(Root::Array(a)) => Value::Array(a),
+            Err(e) => panic!("Want Value, got Error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn try_from() {
+        let s: Result<String, _> = json!("rson").try_into();
+        assert_eq!(s, Ok("rson".to_string()));
+        assert_eq!(f64::try_from(json!(1.5)), Ok(1.5));
+        assert_eq!(i64::try_from(json!(-3)), Ok(-3));
+        assert_eq!(bool::try_from(json!(true)), Ok(true));
+
+        let v = json!(2);
+        assert_eq!(i64::try_from(&v), Ok(2));
+        assert_eq!(f64::try_from(&v), Ok(2.0));
+        assert_eq!(
+            String::try_from(&v),
+            Err(ConvError::TypeMismatch { expected: "string" })
+        );
+        assert_eq!(
+            String::try_from(v),
+            Err(ConvError::TypeMismatch { expected: "string" })
+        );
+        assert_eq!(i64::try_from(json!(2.5)), Err(ConvError::OutOfRange));
+    }
+    #[derive(Debug, PartialEq)]
+    struct Package {
+        name: String,
+        version: Option<String>,
+        keywords: Vec<String>,
+    }
+    from_value_struct!(Package {
+        name,
+        version,
+        keywords,
+    });
+
+    #[test]
+    fn struct_mapping() {
+        let v = parse_value(r#"{"name": "rson", "keywords": ["json"], "extra": 1}"#);
+        assert_eq!(
+            Package::from_value(&v),
+            Ok(Package {
+                name: "rson".to_string(),
+                version: None,
+                keywords: vec!["json".to_string()],
+            })
+        );
+
+        let v = parse_value(r#"{"name": "rson", "version": "0.2.0"}"#);
+        assert_eq!(
+            Package::from_value(&v),
+            Err(ConvError::MissingField("keywords"))
+        );
+        let v = parse_value(r#"{"name": 1, "keywords": []}"#);
+        assert_eq!(
+            Package::from_value(&v),
+            Err(ConvError::TypeMismatch { expected: "string" })
+        );
+        assert_eq!(
+            Package::from_value(&parse_value("[]")),
+            Err(ConvError::TypeMismatch { expected: "object" })
+        );
+    }
+    #[test]
+    fn vec() {
+        let v = parse_value(r#"{"counts": [1, 2, -3], "mixed": [1, "x"]}"#);
+
+        assert_eq!(
+            Vec::<i64>::from_value(v.query("counts").unwrap()),
+            Ok(vec![1, 2, -3])
+        );
+        assert_eq!(
+            Vec::<i64>::from_value(v.query("mixed").unwrap()),
+            Err(ConvError::TypeMismatch { expected: "number" })
+        );
+        assert_eq!(
+            Vec::<i64>::from_value(&v),
+            Err(ConvError::TypeMismatch { expected: "array" })
+        );
+    }
+    #[test]
+    fn option() {
+        let v = parse_value(r#"{"name": "rson", "alias": null, "id": 1}"#);
+
+        assert_eq!(
+            Option::<String>::from_value(v.query("name").unwrap()),
+            Ok(Some("rson".to_string()))
+        );
+        assert_eq!(
+            Option::<String>::from_value(v.query("alias").unwrap()),
+            Ok(None)
+        );
+        assert_eq!(
+            Option::<String>::from_value(v.query("id").unwrap()),
+            Err(ConvError::TypeMismatch { expected: "string" })
+        );
+    }
+    #[test]
+    fn as_int() {
+        assert_eq!(json!(200).as_u8(), Some(200));
+        assert_eq!(json!(300).as_u8(), None);
+        assert_eq!(json!(300).as_u16(), Some(300));
+        assert_eq!(json!(-1).as_u32(), None);
+        assert_eq!(json!(-128).as_i8(), Some(-128));
+        assert_eq!(json!(-129).as_i8(), None);
+        assert_eq!(json!(40000).as_i16(), None);
+        assert_eq!(json!(4_294_967_295u32).as_u32(), Some(u32::MAX));
+        assert_eq!(json!(2_147_483_648i64).as_i32(), None);
+        assert_eq!(Value::Number(1.5).as_i32(), None);
+        assert_eq!(json!("1").as_u8(), None);
+        assert_eq!(json!(-3).as_i64(), Some(-3));
+        assert_eq!(json!(1e19).as_i64(), None);
+        assert_eq!(json!(1e19).as_u64(), Some(10_000_000_000_000_000_000));
+        assert_eq!(json!(-1).as_u64(), None);
+        assert_eq!(json!(2e64).as_u64(), None);
+    }
+    #[test]
+    fn integer_valued() {
+        assert!(json!(2.0).is_integer_valued());
+        assert!(json!(-1e300).is_integer_valued());
+        assert!(!json!(2.5).is_integer_valued());
+        assert!(!json!("2").is_integer_valued());
+
+        assert_eq!(json!(2.0).as_exact_i64(), Some(2));
+        assert_eq!(json!(-2.0).as_exact_i64(), Some(-2));
+        assert_eq!(json!(2.5).as_exact_i64(), None);
+        assert_eq!(json!(1e19).as_exact_i64(), None);
+        assert_eq!(
+            json!(-9_223_372_036_854_775_808.0).as_exact_i64(),
+            Some(i64::MIN)
+        );
+    }
+    #[test]
+    fn primitive() {
+        assert_eq!(bool::from_value(&json!(true)), Ok(true));
+        assert_eq!(f64::from_value(&Value::Number(1.5)), Ok(1.5));
+        assert_eq!(u8::from_value(&json!(255)), Ok(255));
+        assert_eq!(u8::from_value(&json!(256)), Err(ConvError::OutOfRange));
+        assert_eq!(
+            i8::from_value(&Value::Number(-129.0)),
+            Err(ConvError::OutOfRange)
+        );
+        assert_eq!(
+            i64::from_value(&Value::Number(2.5)),
+            Err(ConvError::OutOfRange)
+        );
+        assert_eq!(
+            i64::from_value(&Value::Number(9_223_372_036_854_775_808.0)),
+            Err(ConvError::OutOfRange)
+        );
+        assert_eq!(
+            u32::from_value(&json!("1")),
+            Err(ConvError::TypeMismatch { expected: "number" })
+        );
+    }
+    #[test]
+    #[cfg(feature = "std")]
+    fn map() {
+        let v = parse_value(r#"{"a": 1, "b": 2}"#);
+        let mut want = HashMap::new();
+        want.insert("a".to_string(), 1u32);
+        want.insert("b".to_string(), 2u32);
+
+        assert_eq!(HashMap::<String, u32>::from_value(&v), Ok(want));
+    }
+    #[test]
+    #[cfg(feature = "std")]
+    fn to_value_map() {
+        let mut m = HashMap::new();
+        m.insert("a".to_string(), 1i64);
+        m.insert("b".to_string(), -2i64);
+
+        assert_eq!(m.to_value(), json!({ "a": 1, "b": -2 }));
+    }
+    #[test]
+    fn to_value_vec() {
+        assert_eq!(vec![true, false].to_value(), json!([true, false]));
+        assert_eq!(Vec::<bool>::new().to_value(), json!([]));
+    }
+    #[test]
+    fn to_value_primitive() {
+        assert_eq!("x".to_value(), json!("x"));
+        assert_eq!(Some(1u8).to_value(), json!(1));
+        assert_eq!(None::<String>.to_value(), json!(null));
+        assert_eq!(
+            vec![Some("a".to_string()), None].to_value(),
+            json!(["a", null])
+        );
+    }
+}
@@ -1,39 +1,55 @@
+use std::io;
+use std::io::Write;
+
 use crate::combinator::parse;
 use crate::json::Root;
+use crate::serializer::escape;
+
+/// How `run` reports its result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Rust debug output of the parsed tree or error, for debugging the parser.
+    Debug,
+    /// Compact JSON, with errors as `{"error":"...","position":N}`. The default.
+    Json,
+    /// Like `Json`, but the document is pretty-printed.
+    Pretty,
+}
 
 pub fn run(s: String) {
-    match parse(&s) {
-        Ok(Root::Object(o)) => {
-            println!("{:?}", o);
-        }
-        Ok(Root::Array(a)) => {
-            println!("{:?}", a);
+    run_with_format(&s, Format::Json, &mut io::stdout()).ok();
+}
+
+pub fn run_with_format<W: Write>(s: &str, format: Format, w: &mut W) -> io::Result<()> {
+    match (parse(s), format) {
+        (Ok(Root::Object(o)), Format::Debug) => writeln!(w, "{:?}", o),
+        (Ok(Root::Array(a)), Format::Debug) => writeln!(w, "{:?}", a),
+        (Err(e), Format::Debug) => writeln!(w, "{:?}", e),
+        (Ok(root), Format::Json) => writeln!(w, "{}", root.to_json_string()),
+        (Ok(root), Format::Pretty) => write!(w, "{}", root.to_json_pretty_2space()),
+        (Err(e), _) => {
+            let position = match e.position() {
+                Some(p) => p.to_string(),
+                None => "null".to_string(),
+            };
+            writeln!(
+                w,
+                "{{\"error\":\"{}\",\"position\":{}}}",
+                escape(&e.to_string()),
+                position
+            )
         }
-        Err(e) => println!("{:?}", e),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use crate::combinator::{parse, Error};
+    use crate::ep::{run_with_format, Format};
     use crate::json::Object;
     use crate::json::Root;
     use crate::json::Value;
 
-    macro_rules! hash {
-        ( $( $t:expr),* ) => {
-            {
-                let mut temp_hash = HashMap::new();
-                $(
-                    temp_hash.insert($t.0, $t.1);
-                )*
-                temp_hash
-            }
-        };
-    }
-
     #[test]
     fn parse_full() {
         let input = r##"
@@ -78,13 +94,32 @@ mod tests {
         assert_object(want, got)
     }
 
+    #[test]
+    fn json_format() {
+        let run = |s| {
+            let mut out = vec![];
+            run_with_format(s, Format::Json, &mut out).unwrap();
+            std::string::String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(
+            run("[1, \"x\", {\"a\"}]"),
+            "{\"error\":\"Expected `:` after key at byte 13\",\"position\":13}\n"
+        );
+        assert_eq!(
+            run("[1] 2"),
+            "{\"error\":\"Found unterminated json at byte 4\",\"position\":4}\n"
+        );
+        assert_eq!(run(" [1, \"x\", {}] "), "[1,\"x\",{}]\n");
+    }
+
     fn assert_object(want: Object, got: Result<Root, Error>) {
         match got {
             Ok(Root::Object(o)) => assert_eq!(want, o),
-            Ok(Root::Array(a)) => unreachable!(),
+            Ok(Root::Array(_)) => unreachable!(),
             Err(e) => {
                 println!("{:?}", e);
-                assert!(false, "Want Object, got Error {:?}", e)
+                panic!("Want Object, got Error {:?}", e)
             }
         }
     }
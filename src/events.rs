@@ -0,0 +1,211 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::combinator::Error;
+use crate::json::Value;
+use crate::lexer::{Lexer, Token};
+
+/// A step through a JSON document, as produced by `parse_events`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartObject,
+    /// The key of the object member whose value comes next.
+    Key(String),
+    StartArray,
+    /// A scalar: a string, number, boolean or null.
+    Value(Value),
+    EndArray,
+    EndObject,
+}
+
+/// Parses `s` into a stream of events without building the tree, so that
+/// memory use is bounded by the nesting depth rather than the document size.
+/// Input is checked as it is read: an error is yielded in place of the event
+/// it occurs in, and ends the stream.
+pub fn parse_events(s: &str) -> impl Iterator<Item = Result<Event, Error>> + '_ {
+    Events {
+        lexer: Lexer::new(s),
+        input: s,
+        stack: vec![],
+        expect: Expect::Root,
+        done: false,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Expect {
+    Root,
+    FirstKey,
+    Key,
+    Colon,
+    FirstValue,
+    Value,
+    CommaOrClose,
+    End,
+}
+
+struct Events<'a> {
+    lexer: Lexer<'a>,
+    input: &'a str,
+    // Open containers, `true` for objects, with the offset of their opening bracket.
+    stack: Vec<(bool, usize)>,
+    expect: Expect,
+    done: bool,
+}
+
+impl<'a> Events<'a> {
+    // Returns `None` for tokens that only separate events.
+    fn event(&mut self, token: Token, start: usize) -> Result<Option<Event>, Error> {
+        let in_object = self.stack.last().map(|&(object, _)| object);
+        let event = match (self.expect, token) {
+            (Expect::End, _) => return Err(Error::FoundUnTerminatedError(start)),
+            (_, Token::Illegal(p, _)) => return Err(Error::ParseError(p)),
+            (Expect::Colon, Token::Colon) => {
+                self.expect = Expect::Value;
+                return Ok(None);
+            }
+            (Expect::CommaOrClose, Token::Comma) => {
+                self.expect = if in_object == Some(true) {
+                    Expect::Key
+                } else {
+                    Expect::Value
+                };
+                return Ok(None);
+            }
+            (Expect::FirstKey, Token::String(k)) | (Expect::Key, Token::String(k)) => {
+                self.expect = Expect::Colon;
+                Event::Key(k)
+            }
+            (Expect::FirstKey, Token::RightBrace) | (Expect::CommaOrClose, Token::RightBrace)
+                if in_object == Some(true) =>
+            {
+                self.end_value(true);
+                Event::EndObject
+            }
+            (Expect::FirstValue, Token::RightBracket)
+            | (Expect::CommaOrClose, Token::RightBracket)
+                if in_object == Some(false) =>
+            {
+                self.end_value(true);
+                Event::EndArray
+            }
+            (Expect::Root, Token::LeftBrace)
+            | (Expect::FirstValue, Token::LeftBrace)
+            | (Expect::Value, Token::LeftBrace) => {
+                self.stack.push((true, start));
+                self.expect = Expect::FirstKey;
+                Event::StartObject
+            }
+            (Expect::Root, Token::LeftBracket)
+            | (Expect::FirstValue, Token::LeftBracket)
+            | (Expect::Value, Token::LeftBracket) => {
+                self.stack.push((false, start));
+                self.expect = Expect::FirstValue;
+                Event::StartArray
+            }
+            (Expect::FirstValue, token) | (Expect::Value, token) => {
+                let v = match token {
+                    Token::String(s) => Value::String(s),
+                    Token::Number(n) => Value::Number(n),
+                    Token::Boolean(b) => Value::Boolean(b),
+                    Token::Null => Value::Null,
+                    _ => return Err(Error::ParseError(start)),
+                };
+                self.end_value(false);
+                Event::Value(v)
+            }
+            (Expect::Colon, _) => return Err(Error::MissingColon(start)),
+            (_, Token::Colon) if in_object == Some(false) => {
+                return Err(Error::UnexpectedColon(start))
+            }
+            _ => return Err(Error::ParseError(start)),
+        };
+        Ok(Some(event))
+    }
+
+    // Updates the state after a complete value, popping its container if `close`.
+    fn end_value(&mut self, close: bool) {
+        if close {
+            self.stack.pop();
+        }
+        self.expect = if self.stack.is_empty() {
+            Expect::End
+        } else {
+            Expect::CommaOrClose
+        };
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let next = match self.lexer.next_spanned() {
+                Some((token, span)) => self.event(token, span.start).transpose(),
+                None => match (self.stack.last(), self.expect) {
+                    (Some(&(_, open)), _) => Some(Err(Error::UnclosedContainer(open))),
+                    (None, Expect::Root) => Some(Err(Error::ParseError(self.input.len()))),
+                    _ => {
+                        self.done = true;
+                        None
+                    }
+                },
+            };
+            if let Some(next) = next {
+                self.done = next.is_err();
+                return Some(next);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combinator::Error;
+    use crate::events::{parse_events, Event};
+    use crate::json::Value;
+
+    #[test]
+    fn events() {
+        let events: Vec<_> = parse_events(r#"{"a": [1, {"b": null}], "c": "x"}"#).collect();
+
+        assert_eq!(
+            events,
+            vec![
+                Ok(Event::StartObject),
+                Ok(Event::Key("a".to_string())),
+                Ok(Event::StartArray),
+                Ok(Event::Value(Value::Number(1.0))),
+                Ok(Event::StartObject),
+                Ok(Event::Key("b".to_string())),
+                Ok(Event::Value(Value::Null)),
+                Ok(Event::EndObject),
+                Ok(Event::EndArray),
+                Ok(Event::Key("c".to_string())),
+                Ok(Event::Value(Value::String("x".to_string()))),
+                Ok(Event::EndObject),
+            ]
+        );
+        assert_eq!(
+            parse_events("[]").collect::<Vec<_>>(),
+            vec![Ok(Event::StartArray), Ok(Event::EndArray)]
+        );
+    }
+    #[test]
+    fn errors() {
+        let last = |s| parse_events(s).last().unwrap();
+
+        assert_eq!(last("[1 2]"), Err(Error::ParseError(3)));
+        assert_eq!(last("{\"a\" 1}"), Err(Error::MissingColon(5)));
+        assert_eq!(last("[1 : 2]"), Err(Error::UnexpectedColon(3)));
+        assert_eq!(last("[1,]"), Err(Error::ParseError(3)));
+        assert_eq!(last("[x]"), Err(Error::ParseError(1)));
+        assert_eq!(last("1"), Err(Error::ParseError(0)));
+        assert_eq!(last(""), Err(Error::ParseError(0)));
+        assert_eq!(last("{\"a\": [1"), Err(Error::UnclosedContainer(6)));
+        assert_eq!(last("[1] 2"), Err(Error::FoundUnTerminatedError(4)));
+    }
+}
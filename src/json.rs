@@ -1,12 +1,55 @@
+#[cfg(feature = "btree_map")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "preserve_order")]
+use indexmap::IndexMap;
+#[cfg(not(any(feature = "btree_map", feature = "preserve_order")))]
 use std::collections::HashMap;
 
-use std::fmt;
-use std::fmt::Display;
-use std::fmt::Formatter;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+use crate::combinator::{parse_number, ParseOptions};
+use crate::serializer;
+
+#[cfg(all(feature = "btree_map", feature = "preserve_order"))]
+compile_error!("the `btree_map` and `preserve_order` features are mutually exclusive");
+#[cfg(not(any(feature = "std", feature = "btree_map")))]
+compile_error!("without the `std` feature, enable `btree_map`: `alloc` has no `HashMap`");
+
+/// Members of an object. With the `btree_map` feature this is a `BTreeMap`,
+/// which iterates in sorted key order. With the `preserve_order` feature it is
+/// an `IndexMap`, which iterates in insertion order, so parsed objects keep
+/// the order of the document. Without the `std` feature it must be a `BTreeMap`.
+#[cfg(not(any(feature = "btree_map", feature = "preserve_order")))]
+pub type Object = HashMap<Key, Value>;
+#[cfg(feature = "btree_map")]
+pub type Object = BTreeMap<Key, Value>;
+#[cfg(feature = "preserve_order")]
+pub type Object = IndexMap<Key, Value>;
+
+/// Key of an object member. Keys are shared rather than owned, so that a
+/// `combinator::Parser` with `intern_keys` allocates each distinct key once,
+/// however often it repeats. `Arc` keeps trees `Send + Sync`.
+pub type Key = Arc<str>;
+
+#[cfg(not(feature = "btree_map"))]
+pub(crate) fn reserve_members(o: &mut Object, additional: usize) {
+    o.reserve(additional);
+}
+
+// `BTreeMap` has no capacity to reserve.
+#[cfg(feature = "btree_map")]
+pub(crate) fn reserve_members(_: &mut Object, _: usize) {}
 
-pub type Object = HashMap<String, Value>;
 pub type Array = Vec<Value>;
 
+/// `Root` and `Value` own all of their data, so parsed trees are `Send + Sync`
+/// and can be handed to other threads. New variants must keep it that way,
+/// e.g. by using `Arc` rather than `Rc` for shared data.
 #[derive(Clone, PartialEq)]
 pub enum Root {
     Object(Object),
@@ -23,51 +66,551 @@ pub enum Value {
     Number(f64),
 }
 
-impl Display for Root {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match self {
-            Root::Object(o) => write!(f, "{:?}", o),
-            Root::Array(a) => write!(f, "{:?}", a),
+/// A JSON number, split into integers that fit an `i64` and everything else.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Number::Int(n) => n as f64,
+            Number::Float(n) => n,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Number::Int(n) => Some(n),
+            Number::Float(_) => None,
         }
     }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Number::Int(_))
+    }
 }
 
-impl fmt::Debug for Root {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+impl Value {
+    pub fn empty_object() -> Value {
+        Value::Object(Object::new())
+    }
+
+    pub fn empty_array() -> Value {
+        Value::Array(Array::new())
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Object> {
+        match self {
+            Value::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Array> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// The UTF-8 bytes of a string value, without copying; `None` for anything else.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::String(s) => Some(s.as_bytes()),
+            _ => None,
+        }
+    }
+
+    /// Name of the kind of value, for messages such as "expected X, got Y".
+    /// Numbers without a fractional part are named `"integer"`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Object(_) => "object",
+            Value::Array(_) => "array",
+            Value::String(_) => "string",
+            Value::Number(_) if self.is_integer_valued() => "integer",
+            Value::Number(_) => "number",
+            Value::Boolean(_) => "boolean",
+            Value::Null => "null",
+        }
+    }
+
+    /// Number of members or elements, or `None` for scalars.
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Value::Object(o) => Some(o.len()),
+            Value::Array(a) => Some(a.len()),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|n| n == 0)
+    }
+
+    /// True for numbers without a fractional part, however large.
+    pub fn is_integer_valued(&self) -> bool {
+        match self {
+            Value::Number(n) => n.is_finite() && n % 1.0 == 0.0,
+            _ => false,
+        }
+    }
+
+    /// The number as `Number::Int` when it is integer valued and fits an `i64`,
+    /// otherwise as `Number::Float`. `None` for non-numbers.
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            Value::Number(n) => Some(match self.as_exact_i64() {
+                Some(i) => Number::Int(i),
+                None => Number::Float(*n),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The number, or the number a string holds in JSON syntax, ignoring
+    /// surrounding whitespace, as in `"42"` or `" -1.5e3 "`. `None` otherwise.
+    pub fn coerce_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::String(s) => match parse_number(s.trim(), &ParseOptions::strict()) {
+                Ok(("", Value::Number(n))) => Some(n),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// True only for objects that have `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        match self {
+            Value::Object(o) => o.contains_key(key),
+            _ => false,
+        }
+    }
+
+    /// The member `key` of an object, or `default` when there is none.
+    pub fn get_or<'a>(&'a self, key: &str, default: &'a Value) -> &'a Value {
+        self.members().and_then(|o| o.get(key)).unwrap_or(default)
+    }
+
+    /// The string member `key` of an object, or `default` when it is missing or
+    /// not a string.
+    pub fn as_str_or<'a>(&'a self, key: &str, default: &'a str) -> &'a str {
+        match self.members().and_then(|o| o.get(key)) {
+            Some(Value::String(s)) => s,
+            _ => default,
+        }
+    }
+
+    /// The string member `key` of an object, or `None` when it is missing, not
+    /// a string or empty, so that a blank setting reads as an absent one.
+    pub fn get_non_empty_str(&self, key: &str) -> Option<&str> {
+        match self.members().and_then(|o| o.get(key)) {
+            Some(Value::String(s)) if !s.is_empty() => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Looks up a member of an object, ignoring case. An exact match wins; among
+    /// several keys differing only in case, the lexicographically smallest is used.
+    pub fn get_ci(&self, key: &str) -> Option<&Value> {
+        let o = self.members()?;
+        o.get(key).or_else(|| {
+            let key = key.to_lowercase();
+            o.iter()
+                .filter(|(k, _)| k.to_lowercase() == key)
+                .min_by_key(|(k, _)| *k)
+                .map(|(_, v)| v)
+        })
+    }
+
+    /// Keys of an object value; empty for anything else.
+    pub fn keys(&self) -> impl Iterator<Item = &Key> {
+        self.members().into_iter().flat_map(|o| o.keys())
+    }
+
+    /// Member values of an object value; empty for anything else.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.members().into_iter().flat_map(|o| o.values())
+    }
+
+    /// Elements of an array value, mutably; empty for anything else.
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.as_array_mut().into_iter().flat_map(|a| a.iter_mut())
+    }
+
+    /// Members of an object value, with mutable values; empty for anything else.
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = (&Key, &mut Value)> {
+        self.as_object_mut().into_iter().flat_map(|o| o.iter_mut())
+    }
+
+    fn members(&self) -> Option<&Object> {
+        match self {
+            Value::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Keeps only the object members for which `f` returns true. Other values
+    /// are left unchanged.
+    pub fn retain<F: FnMut(&str, &Value) -> bool>(&mut self, mut f: F) {
+        if let Value::Object(o) = self {
+            o.retain(|k, v| f(k, v));
+        }
+    }
+
+    /// Keeps only the array elements for which `f` returns true. Other values
+    /// are left unchanged.
+    pub fn retain_array<F: FnMut(&Value) -> bool>(&mut self, f: F) {
+        if let Value::Array(a) = self {
+            a.retain(f);
+        }
+    }
+
+    /// True only for arrays with an element equal to `needle`.
+    pub fn array_contains(&self, needle: &Value) -> bool {
+        match self {
+            Value::Array(a) => a.contains(needle),
+            _ => false,
+        }
+    }
+
+    /// Equality that compares arrays as multisets, at any depth: each element
+    /// must match a distinct element of the other array, in any order.
+    pub fn eq_ignore_array_order(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Array(a), Value::Array(b)) => {
+                let mut unmatched: Vec<&Value> = b.iter().collect();
+                a.len() == b.len()
+                    && a.iter().all(|x| {
+                        let i = unmatched.iter().position(|y| x.eq_ignore_array_order(y));
+                        i.map(|i| unmatched.swap_remove(i)).is_some()
+                    })
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(k, x)| b.get(k).is_some_and(|y| x.eq_ignore_array_order(y)))
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// Returns a slice of an array value, or `None` for other values or a range out of bounds.
+    pub fn get_range(&self, range: core::ops::Range<usize>) -> Option<&[Value]> {
+        match self {
+            Value::Array(a) => a.get(range),
+            _ => None,
+        }
+    }
+
+    /// Looks up a nested value by a dotted path such as `info.version` or `keywords.0`.
+    pub fn query(&self, path: &str) -> Option<&Value> {
+        path.split('.').try_fold(self, |v, segment| match v {
+            Value::Object(o) => o.get(segment),
+            Value::Array(a) => segment.parse::<usize>().ok().and_then(|i| a.get(i)),
+            _ => None,
+        })
+    }
+
+    /// Calls `f` with the JSON Pointer and value of every node, parents before children.
+    pub fn walk<F: FnMut(&str, &Value)>(&self, mut f: F) {
+        self.walk_at(&mut String::new(), &mut f);
+    }
+
+    /// Like `walk`, but `f` may edit each node before its children are visited.
+    pub fn walk_mut<F: FnMut(&str, &mut Value)>(&mut self, mut f: F) {
+        self.walk_mut_at(&mut String::new(), &mut f);
+    }
+
+    /// Number of nodes, this one included, for which `pred` returns true.
+    pub fn count_matching<F: Fn(&Value) -> bool>(&self, pred: F) -> usize {
+        let mut count = 0;
+        self.walk(|_, v| {
+            if pred(v) {
+                count += 1;
+            }
+        });
+        count
+    }
+
+    /// Rebuilds the tree with every scalar leaf replaced by `f` applied to it.
+    /// Containers themselves are kept, including empty ones.
+    pub fn deep_map<F: FnMut(Value) -> Value>(self, mut f: F) -> Value {
+        self.deep_map_with(&mut f)
+    }
+
+    /// Sorts the members of every object, at any depth, by key, so that they
+    /// iterate and serialize in that order. Only objects that keep an order of
+    /// their own, with the `preserve_order` feature, are changed: a `BTreeMap`
+    /// is always in key order, and a `HashMap` is serialized in key order.
+    pub fn sort_keys(&mut self) {
+        #[cfg(feature = "preserve_order")]
+        self.walk_mut(|_, v| {
+            if let Value::Object(o) = v {
+                o.sort_keys();
+            }
+        });
+    }
+
+    /// Replaces the value of every member named in `keys`, compared case-insensitively,
+    /// with the string `placeholder`, at any depth.
+    pub fn redact(&mut self, keys: &[&str], placeholder: &str) {
+        let keys: Vec<String> = keys.iter().map(|k| k.to_lowercase()).collect();
+        self.walk_mut(|_, v| {
+            if let Value::Object(o) = v {
+                for (k, v) in o.iter_mut() {
+                    if keys.contains(&k.to_lowercase()) {
+                        *v = Value::String(placeholder.to_string());
+                    }
+                }
+            }
+        });
+    }
+
+    fn walk_at<F: FnMut(&str, &Value)>(&self, pointer: &mut String, f: &mut F) {
+        f(pointer, self);
+        let len = pointer.len();
+        match self {
+            Value::Object(o) => {
+                for (k, v) in o {
+                    push_pointer_token(pointer, k);
+                    v.walk_at(pointer, f);
+                    pointer.truncate(len);
+                }
+            }
+            Value::Array(a) => {
+                for (i, v) in a.iter().enumerate() {
+                    push_pointer_token(pointer, &i.to_string());
+                    v.walk_at(pointer, f);
+                    pointer.truncate(len);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn walk_mut_at<F: FnMut(&str, &mut Value)>(&mut self, pointer: &mut String, f: &mut F) {
+        f(pointer, self);
+        let len = pointer.len();
+        match self {
+            Value::Object(o) => {
+                for (k, v) in o {
+                    push_pointer_token(pointer, k);
+                    v.walk_mut_at(pointer, f);
+                    pointer.truncate(len);
+                }
+            }
+            Value::Array(a) => {
+                for (i, v) in a.iter_mut().enumerate() {
+                    push_pointer_token(pointer, &i.to_string());
+                    v.walk_mut_at(pointer, f);
+                    pointer.truncate(len);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn deep_map_with<F: FnMut(Value) -> Value>(self, f: &mut F) -> Value {
+        match self {
+            Value::Object(o) => Value::Object(
+                o.into_iter()
+                    .map(|(k, v)| (k, v.deep_map_with(f)))
+                    .collect(),
+            ),
+            Value::Array(a) => Value::Array(a.into_iter().map(|v| v.deep_map_with(f)).collect()),
+            v => f(v),
+        }
+    }
+
+    /// Resolves an RFC 6901 JSON Pointer such as `/keywords/0`.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        self.try_pointer(pointer).ok()
+    }
+
+    /// Resolves each of `pointers`, the results being in the same order.
+    pub fn pointer_many(&self, pointers: &[&str]) -> Vec<Option<&Value>> {
+        pointers.iter().map(|p| self.pointer(p)).collect()
+    }
+
+    /// Like `pointer`, but tells why resolution failed.
+    pub fn try_pointer(&self, pointer: &str) -> Result<&Value, PointerError> {
+        if pointer.is_empty() {
+            return Ok(self);
+        }
+        if !pointer.starts_with('/') {
+            return Err(PointerError::InvalidPointer);
+        }
+        pointer[1..].split('/').try_fold(self, |v, token| {
+            let token = unescape_pointer_token(token);
+            match v {
+                Value::Object(o) => o.get(token.as_str()).ok_or(PointerError::NoSuchKey(token)),
+                Value::Array(a) => parse_index(&token)
+                    .and_then(|i| a.get(i))
+                    .ok_or(PointerError::IndexOutOfRange(token)),
+                _ => Err(PointerError::NotAContainer(token)),
+            }
+        })
+    }
+
+    /// The JSON Patch (RFC 6902) `add` operation: sets the member the pointer names,
+    /// or inserts into an array before the indexed element, shifting the rest.
+    /// The last token may be `-` to append to an array. The empty pointer
+    /// replaces the whole value.
+    pub fn pointer_add(&mut self, pointer: &str, value: Value) -> Result<(), PointerError> {
+        if pointer.is_empty() {
+            *self = value;
+            return Ok(());
+        }
+        let (parent, last) = match pointer.rfind('/') {
+            Some(i) if pointer.starts_with('/') => (&pointer[..i], &pointer[i + 1..]),
+            _ => return Err(PointerError::InvalidPointer),
+        };
+        let token = unescape_pointer_token(last);
+        match self.try_pointer_mut(parent)? {
+            Value::Object(o) => {
+                o.insert(token.into(), value);
+            }
+            Value::Array(a) => {
+                let i = match token.as_str() {
+                    "-" => a.len(),
+                    _ => parse_index(&token)
+                        .filter(|&i| i <= a.len())
+                        .ok_or(PointerError::IndexOutOfRange(token))?,
+                };
+                a.insert(i, value);
+            }
+            _ => return Err(PointerError::NotAContainer(token)),
+        }
+        Ok(())
+    }
+
+    fn try_pointer_mut(&mut self, pointer: &str) -> Result<&mut Value, PointerError> {
+        if pointer.is_empty() {
+            return Ok(self);
+        }
+        pointer[1..].split('/').try_fold(self, |v, token| {
+            let token = unescape_pointer_token(token);
+            match v {
+                Value::Object(o) => o
+                    .get_mut(token.as_str())
+                    .ok_or(PointerError::NoSuchKey(token)),
+                Value::Array(a) => match parse_index(&token) {
+                    Some(i) if i < a.len() => Ok(&mut a[i]),
+                    _ => Err(PointerError::IndexOutOfRange(token)),
+                },
+                _ => Err(PointerError::NotAContainer(token)),
+            }
+        })
+    }
+
+    /// Like `query`, returning a mutable reference.
+    pub fn query_mut(&mut self, path: &str) -> Option<&mut Value> {
+        path.split('.').try_fold(self, |v, segment| match v {
+            Value::Object(o) => o.get_mut(segment),
+            Value::Array(a) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(move |i| a.get_mut(i)),
+            _ => None,
+        })
+    }
+
+    /// The object or array as a `Root`, or `None` for scalars.
+    pub fn try_into_root(self) -> Option<Root> {
         match self {
-            Root::Object(o) => write!(f, "Object({:?})", o),
-            Root::Array(a) => write!(f, "Array({:?})", a),
+            Value::Object(o) => Some(Root::Object(o)),
+            Value::Array(a) => Some(Root::Array(a)),
+            _ => None,
         }
     }
 }
 
-impl Display for Value {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+impl Root {
+    pub fn into_value(self) -> Value {
         match self {
-            Value::Boolean(b) => write!(f, "{}", b),
-            Value::Null => write!(f, "null"),
-            Value::String(s) => write!(f, "{}", s),
-            Value::Number(n) => write!(f, "{}", n),
-            Value::Object(o) => write!(f, "{:?}", o),
-            Value::Array(a) => write!(f, "{:?}", a),
+            Root::Object(o) => Value::Object(o),
+            Root::Array(a) => Value::Array(a),
         }
     }
 }
 
-impl fmt::Debug for Value {
+/// Why a JSON Pointer did not resolve, with the segment (unescaped) where it failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerError {
+    /// The pointer is neither empty nor starts with `/`.
+    InvalidPointer,
+    NoSuchKey(String),
+    /// The segment is not an index of the array.
+    IndexOutOfRange(String),
+    /// The segment was applied to a scalar.
+    NotAContainer(String),
+}
+
+impl Display for PointerError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            Value::Boolean(b) => write!(f, "Boolean({})", b),
-            Value::Null => write!(f, "Null"),
+            PointerError::InvalidPointer => write!(f, "Pointer must be empty or start with '/'"),
+            PointerError::NoSuchKey(k) => write!(f, "No such key: {}", k),
+            PointerError::IndexOutOfRange(i) => write!(f, "Index out of range: {}", i),
+            PointerError::NotAContainer(s) => write!(f, "Not a container at: {}", s),
+        }
+    }
+}
+
+// Array indices in a JSON Pointer are decimal without leading zeros.
+fn parse_index(token: &str) -> Option<usize> {
+    if !token.bytes().all(|b| b.is_ascii_digit()) || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token.parse().ok()
+}
 
-            Value::String(s) => write!(f, "String({})", s),
-            Value::Number(n) => write!(f, "Number({})", n),
-            Value::Object(o) => write!(f, "{:?}", o),
-            Value::Array(a) => write!(f, "{:?}", a),
+fn unescape_pointer_token(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+// Appends `/token` to a JSON Pointer, escaping `~` and `/` as RFC 6901 requires.
+pub(crate) fn push_pointer_token(pointer: &mut String, token: &str) {
+    pointer.push('/');
+    for c in token.chars() {
+        match c {
+            '~' => pointer.push_str("~0"),
+            '/' => pointer.push_str("~1"),
+            c => pointer.push(c),
         }
     }
 }
 
+impl Display for Root {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        serializer::write_root(f, self)
+    }
+}
+
+impl fmt::Debug for Root {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        serializer::write_root(f, self)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        serializer::write_value(f, self)
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        serializer::write_value(f, self)
+    }
+}
+
 impl From<bool> for Value {
     fn from(b: bool) -> Value {
         Value::Boolean(b)
@@ -98,43 +641,232 @@ macro_rules! from_num_for_json {
     };
 }
 
-from_num_for_json!(i8 i16 i32 u8 u16 u32 u64 usize isize f32 f64);
+from_num_for_json!(i8 i16 i32 i64 u8 u16 u32 u64 usize isize f32 f64);
 
+impl PartialEq<str> for Value {
+    fn eq(&self, other: &str) -> bool {
+        matches!(self, Value::String(s) if s == other)
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Value {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<f64> for Value {
+    fn eq(&self, other: &f64) -> bool {
+        matches!(self, Value::Number(n) if n == other)
+    }
+}
+
+impl PartialEq<i64> for Value {
+    fn eq(&self, other: &i64) -> bool {
+        self.as_exact_i64() == Some(*other)
+    }
+}
+
+impl PartialEq<bool> for Value {
+    fn eq(&self, other: &bool) -> bool {
+        matches!(self, Value::Boolean(b) if b == other)
+    }
+}
+
+// Values may be `null`, nested `[...]`/`{...}` literals or any expression
+// convertible with `Into<Value>`. Arrays and objects are munched one element at
+// a time so that multi-token expressions such as `-1.5` or `user.id` work.
 #[allow(unused_macros)]
 macro_rules! json {
     (null) => {
-        Value::Null
+        $crate::json::Value::Null
+    };
+    ([ $( $tt:tt )* ]) => {
+        $crate::json::Value::Array(json!(@array [] $( $tt )*))
+    };
+    ({ $( $tt:tt )* }) => {{
+        #[allow(unused_mut)]
+        let mut object = $crate::json::Object::new();
+        json!(@object object ($( $tt )*));
+        $crate::json::Value::Object(object)
+    }};
+    ($other:expr) => {
+        $crate::json::Value::from($other)
+    };
+
+    (@array [ $( $elements:expr, )* ]) => {
+        vec![ $( $elements, )* ]
+    };
+    (@array [ $( $elements:expr, )* ] null $( , $( $rest:tt )* )?) => {
+        json!(@array [ $( $elements, )* json!(null), ] $( $( $rest )* )?)
+    };
+    (@array [ $( $elements:expr, )* ] [ $( $array:tt )* ] $( , $( $rest:tt )* )?) => {
+        json!(@array [ $( $elements, )* json!([ $( $array )* ]), ] $( $( $rest )* )?)
+    };
+    (@array [ $( $elements:expr, )* ] { $( $map:tt )* } $( , $( $rest:tt )* )?) => {
+        json!(@array [ $( $elements, )* json!({ $( $map )* }), ] $( $( $rest )* )?)
+    };
+    (@array [ $( $elements:expr, )* ] $next:expr, $( $rest:tt )*) => {
+        json!(@array [ $( $elements, )* json!($next), ] $( $rest )*)
+    };
+    (@array [ $( $elements:expr, )* ] $last:expr) => {
+        json!(@array [ $( $elements, )* json!($last), ])
+    };
+
+    (@object $object:ident ()) => {};
+    (@object $object:ident ($key:tt : null $( , $( $rest:tt )* )?)) => {
+        $object.insert($crate::json::Key::from(($key).to_string()), json!(null));
+        json!(@object $object ($( $( $rest )* )?));
     };
-    ([ $( $element:tt ),* ]) =>{
-        Value::Array(vec![ $( json!($element) ),*])
+    (@object $object:ident ($key:tt : [ $( $array:tt )* ] $( , $( $rest:tt )* )?)) => {
+        $object.insert($crate::json::Key::from(($key).to_string()), json!([ $( $array )* ]));
+        json!(@object $object ($( $( $rest )* )?));
     };
-    ({ $( $key:tt : $value:tt),* }) =>{
-        Value::Object(vec![
-            $( ($key.to_string(), json!($value)) ),*
-        ].into_iter().collect())
+    (@object $object:ident ($key:tt : { $( $map:tt )* } $( , $( $rest:tt )* )?)) => {
+        $object.insert($crate::json::Key::from(($key).to_string()), json!({ $( $map )* }));
+        json!(@object $object ($( $( $rest )* )?));
     };
-    ($other:tt) =>{
-        Value::from($other)
+    (@object $object:ident ($key:tt : $value:expr, $( $rest:tt )*)) => {
+        $object.insert($crate::json::Key::from(($key).to_string()), json!($value));
+        json!(@object $object ($( $rest )*));
+    };
+    (@object $object:ident ($key:tt : $value:expr)) => {
+        $object.insert($crate::json::Key::from(($key).to_string()), json!($value));
     };
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::json::Value;
-    use std::collections::HashMap;
-
-    macro_rules! hash {
-        ( $( $t:expr),* ) => {
-            {
-                let mut temp_hash = HashMap::new();
-                $(
-                    temp_hash.insert($t.0, $t.1);
-                )*
-                temp_hash
+    use crate::json::{Number, Object, PointerError, Root, Value};
+
+    #[cfg(feature = "btree_map")]
+    #[test]
+    fn btree_map_sorted() {
+        let v = crate::combinator::parse(r#"{"b": 1, "a": 2, "c": {"z": 0, "y": 0}}"#)
+            .unwrap()
+            .into_value();
+
+        assert_eq!(v.keys().map(|k| &**k).collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(
+            v.query("c")
+                .unwrap()
+                .keys()
+                .map(|k| &**k)
+                .collect::<Vec<_>>(),
+            ["y", "z"]
+        );
+    }
+    #[test]
+    fn as_bytes() {
+        assert_eq!(json!("aé").as_bytes(), Some(&b"a\xc3\xa9"[..]));
+        assert_eq!(json!("").as_bytes(), Some(&[][..]));
+        assert_eq!(json!(1).as_bytes(), None);
+        assert_eq!(json!(["a"]).as_bytes(), None);
+    }
+    #[test]
+    fn root_value_conversion() {
+        let v = json!({ "a": [1, null] });
+        let root = v.clone().try_into_root().unwrap();
+        assert!(matches!(root, Root::Object(_)));
+        assert_eq!(root.into_value(), v);
+
+        let root = Root::Array(vec![json!(true)]);
+        assert_eq!(root.clone().into_value().try_into_root(), Some(root));
+
+        assert_eq!(json!("x").try_into_root(), None);
+        assert_eq!(json!(null).try_into_root(), None);
+    }
+    #[test]
+    fn iter_mut() {
+        let mut v = json!([1, 2.5, "x"]);
+        for e in v.elements_mut() {
+            if let Value::Number(n) = e {
+                *n += 1.0;
             }
-        };
+        }
+        assert_eq!(v, json!([2, 3.5, "x"]));
+        assert_eq!(v.entries_mut().count(), 0);
+
+        let mut v = json!({ "a": "x", "b": [] });
+        for (k, e) in v.entries_mut() {
+            *e = Value::String(format!("{}: {}", k, e.type_name()));
+        }
+        assert_eq!(v, json!({ "a": "a: string", "b": "b: array" }));
+        assert_eq!(v.elements_mut().count(), 0);
+        assert_eq!(json!(null).elements_mut().count(), 0);
+    }
+    #[test]
+    fn type_name() {
+        assert_eq!(json!({}).type_name(), "object");
+        assert_eq!(json!([]).type_name(), "array");
+        assert_eq!(json!("1").type_name(), "string");
+        assert_eq!(json!(1.5).type_name(), "number");
+        assert_eq!(json!(-3).type_name(), "integer");
+        assert_eq!(json!(1e300).type_name(), "integer");
+        assert_eq!(Value::Number(f64::NAN).type_name(), "number");
+        assert_eq!(json!(false).type_name(), "boolean");
+        assert_eq!(json!(null).type_name(), "null");
+    }
+    #[test]
+    fn display_as_json() {
+        assert_eq!(json!("a \"b\"\n").to_string(), r#""a \"b\"\n""#);
+        assert_eq!(json!(1e100).to_string(), "1e100");
+        assert_eq!(json!(-0.0).to_string(), "0");
+        assert_eq!(Value::Number(f64::NAN).to_string(), "null");
+        for v in &[
+            json!("x"),
+            json!(1.5),
+            json!(true),
+            json!(null),
+            json!([1, {"a": "b"}]),
+        ] {
+            assert_eq!(v.to_string(), v.to_json_string());
+        }
+    }
+    #[test]
+    fn eq_primitives() {
+        let v = json!({ "name": "bob", "age": 42, "ratio": 0.5, "admin": false });
+
+        assert!(*v.query("name").unwrap() == "bob");
+        assert_eq!(*v.query("age").unwrap(), 42);
+        assert_eq!(*v.query("ratio").unwrap(), 0.5);
+        assert_eq!(*v.query("admin").unwrap(), false);
+
+        assert_ne!(*v.query("name").unwrap(), "alice");
+        assert_ne!(*v.query("age").unwrap(), 41);
+        assert_ne!(*v.query("ratio").unwrap(), 0);
+        assert_ne!(*v.query("age").unwrap(), true);
+        assert_ne!(json!("42"), 42);
+    }
+    #[test]
+    fn coerce_number() {
+        assert_eq!(json!("42").coerce_number(), Some(42.0));
+        assert_eq!(json!(42).coerce_number(), Some(42.0));
+        assert_eq!(json!(" -1.5e3 ").coerce_number(), Some(-1500.0));
+        assert_eq!(json!("forty-two").coerce_number(), None);
+        assert_eq!(json!("42abc").coerce_number(), None);
+        assert_eq!(json!("").coerce_number(), None);
+        assert_eq!(json!("NaN").coerce_number(), None);
+        assert_eq!(json!("1e999").coerce_number(), None);
+        assert_eq!(json!(true).coerce_number(), None);
     }
+    #[test]
+    fn as_number() {
+        let n = json!(42).as_number().unwrap();
+        assert_eq!(n, Number::Int(42));
+        assert!(n.is_integer());
+        assert_eq!(n.as_i64(), Some(42));
+        assert_eq!(n.as_f64(), 42.0);
 
+        let n = json!(-1.5).as_number().unwrap();
+        assert_eq!(n, Number::Float(-1.5));
+        assert!(!n.is_integer());
+        assert_eq!(n.as_i64(), None);
+        assert_eq!(n.as_f64(), -1.5);
+
+        assert_eq!(json!(1e19).as_number(), Some(Number::Float(1e19)));
+        assert_eq!(json!("1").as_number(), None);
+    }
     #[test]
     fn json_null() {
         assert_eq!(json!(null), Value::Null);
@@ -158,6 +890,15 @@ mod tests {
     #[test]
     fn json_number() {
         assert_eq!(json!(10e10), Value::Number(10e10));
+        assert_eq!(json!(-2), Value::Number(-2.0));
+        assert_eq!(
+            json!([-1, { "a": -2.5 }, -3]),
+            Value::Array(vec![
+                Value::Number(-1.0),
+                Value::Object(hash![("a".to_string(), Value::Number(-2.5))]),
+                Value::Number(-3.0),
+            ])
+        );
     }
     #[test]
     fn json_string() {
@@ -167,4 +908,460 @@ mod tests {
         assert_eq!(json!(x), Value::String("x".to_string()));
     }
 
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn json_interpolation() {
+        let user_id = 42;
+        let tag = "b".to_string();
+        let name = "rson";
+
+        assert_eq!(
+            json!({ "id": user_id, "tags": ["a", tag, null], "name": name.to_uppercase() }),
+            Value::Object(hash![
+                ("id".to_string(), Value::Number(42.0)),
+                (
+                    "tags".to_string(),
+                    Value::Array(vec![
+                        Value::String("a".to_string()),
+                        Value::String("b".to_string()),
+                        Value::Null
+                    ])
+                ),
+                ("name".to_string(), Value::String("RSON".to_string()))
+            ])
+        );
+    }
+    #[test]
+    fn json_expression() {
+        let xs = [1, 2, 3];
+
+        assert_eq!(
+            json!([-1.5, xs.len(), xs[0] + 1, { "nested": [[], {}] },]),
+            Value::Array(vec![
+                Value::Number(-1.5),
+                Value::Number(3.0),
+                Value::Number(2.0),
+                Value::Object(hash![(
+                    "nested".to_string(),
+                    Value::Array(vec![Value::Array(vec![]), Value::Object(Object::new())])
+                )])
+            ])
+        );
+        assert_eq!(
+            json!({ "a": { "b": { "c": xs.len() > 2 } }, }),
+            Value::Object(hash![(
+                "a".to_string(),
+                Value::Object(hash![(
+                    "b".to_string(),
+                    Value::Object(hash![("c".to_string(), Value::Boolean(true))])
+                )])
+            )])
+        );
+    }
+    #[test]
+    fn send_sync() {
+        assert_send_sync::<Value>();
+        assert_send_sync::<crate::json::Root>();
+        assert_send_sync::<crate::combinator::Error>();
+        assert_send_sync::<crate::combinator::ParseOptions>();
+    }
+
+    #[test]
+    fn as_object_mut() {
+        let mut v = json!({ "a": 1 });
+        v.as_object_mut().unwrap().insert("b".into(), json!(true));
+
+        assert_eq!(v, json!({ "a": 1, "b": true }));
+        assert!(json!([]).as_object_mut().is_none());
+        assert!(json!(null).as_object_mut().is_none());
+    }
+
+    #[test]
+    fn as_array_mut() {
+        let mut v = json!([1]);
+        v.as_array_mut().unwrap().push(json!("x"));
+
+        assert_eq!(v, json!([1, "x"]));
+        assert!(json!({}).as_array_mut().is_none());
+        assert!(json!("x").as_array_mut().is_none());
+    }
+
+    #[test]
+    fn len() {
+        let object = json!({ "a": 1, "b": 2 });
+        let array = json!([1, 2, 3]);
+
+        assert_eq!(object.len(), Some(2));
+        assert_eq!(object.is_empty(), Some(false));
+        assert_eq!(array.len(), Some(3));
+        assert_eq!(json!([]).is_empty(), Some(true));
+        assert_eq!(json!({}).is_empty(), Some(true));
+        assert_eq!(json!("abc").len(), None);
+        assert_eq!(json!(null).is_empty(), None);
+    }
+
+    #[test]
+    fn empty_containers() {
+        assert_eq!(Value::empty_object(), Value::Object(Object::new()));
+        assert_eq!(Value::empty_array(), Value::Array(vec![]));
+        assert_eq!(Value::empty_object(), json!({}));
+        assert_eq!(Value::empty_array(), json!([]));
+        assert_ne!(Value::empty_object(), Value::empty_array());
+    }
+
+    #[test]
+    fn walk() {
+        let v = json!({ "a": [1, { "b/c": null }], "d": {}, "e~": "x" });
+
+        let mut leaves = vec![];
+        let mut nodes = 0;
+        v.walk(|pointer, v| {
+            nodes += 1;
+            if v.len().is_none() {
+                leaves.push(pointer.to_string());
+            }
+        });
+        leaves.sort();
+
+        assert_eq!(leaves, ["/a/0", "/a/1/b~1c", "/e~0"]);
+        assert_eq!(nodes, 7);
+
+        let mut order = vec![];
+        json!([[1]]).walk(|pointer, _| order.push(pointer.to_string()));
+        assert_eq!(order, ["", "/0", "/0/0"]);
+    }
+
+    #[test]
+    fn walk_mut() {
+        let mut v = json!({ "a": [1, 2], "b": { "c": 3 } });
+
+        v.walk_mut(|_, v| {
+            if let Value::Number(n) = v {
+                *n *= 10.0;
+            }
+        });
+
+        assert_eq!(v, json!({ "a": [10, 20], "b": { "c": 30 } }));
+    }
+
+    #[test]
+    fn count_matching() {
+        let v = json!({ "a": ["x", 1, { "b": "y", "c": null }], "d": "z", "e": [] });
+
+        assert_eq!(v.count_matching(|v| matches!(v, Value::String(_))), 3);
+        assert_eq!(v.count_matching(|v| *v == Value::Null), 1);
+        assert_eq!(v.count_matching(|v| v.len().is_some()), 4);
+        assert_eq!(json!(1).count_matching(|_| true), 1);
+    }
+
+    #[test]
+    fn deep_map() {
+        let v = json!({ "a": ["x", 1, { "b": "y" }], "c": "z", "d": [] });
+
+        let v = v.deep_map(|v| match v {
+            Value::String(s) => Value::String(s.to_uppercase()),
+            v => v,
+        });
+
+        assert_eq!(v, json!({ "a": ["X", 1, { "b": "Y" }], "c": "Z", "d": [] }));
+    }
+
+    #[test]
+    fn sort_keys() {
+        let mut v =
+            crate::combinator::parse(r#"{"b": 1, "a": {"d": [{"z": 0, "y": 0}], "c": null}}"#)
+                .unwrap()
+                .into_value();
+        if cfg!(feature = "preserve_order") {
+            assert_eq!(v.keys().map(|k| &**k).collect::<Vec<_>>(), ["b", "a"]);
+            assert_eq!(
+                v.to_json_string(),
+                r#"{"b":1,"a":{"d":[{"z":0,"y":0}],"c":null}}"#
+            );
+        }
+
+        v.sort_keys();
+        assert_eq!(
+            v.to_json_string(),
+            r#"{"a":{"c":null,"d":[{"y":0,"z":0}]},"b":1}"#
+        );
+        if cfg!(not(feature = "btree_map")) && cfg!(not(feature = "preserve_order")) {
+            return;
+        }
+        assert_eq!(v.keys().map(|k| &**k).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(
+            v.query("a")
+                .unwrap()
+                .keys()
+                .map(|k| &**k)
+                .collect::<Vec<_>>(),
+            ["c", "d"]
+        );
+    }
+    #[test]
+    fn redact() {
+        let mut v = json!({
+            "user": "alice",
+            "Password": "hunter2",
+            "session": { "TOKEN": "abc", "ttl": 60 },
+            "history": [{ "token": { "value": "x" } }, { "password": null }]
+        });
+
+        v.redact(&["password", "token"], "***");
+
+        assert_eq!(
+            v,
+            json!({
+                "user": "alice",
+                "Password": "***",
+                "session": { "TOKEN": "***", "ttl": 60 },
+                "history": [{ "token": "***" }, { "password": "***" }]
+            })
+        );
+    }
+
+    #[test]
+    fn pointer() {
+        let v = json!({ "a": [1, { "b/c": "x", "~": null }], "s": "text" });
+
+        assert_eq!(v.pointer(""), Some(&v));
+        assert_eq!(v.pointer("/a/0"), Some(&json!(1)));
+        assert_eq!(v.pointer("/a/1/b~1c"), Some(&json!("x")));
+        assert_eq!(v.pointer("/a/1/~0"), Some(&json!(null)));
+        assert_eq!(v.pointer("/missing"), None);
+    }
+
+    #[test]
+    fn pointer_many() {
+        let v = json!({ "name": "rson", "deps": { "nom": "5" }, "tags": ["json"] });
+
+        assert_eq!(
+            v.pointer_many(&["/deps/nom", "/version", "/tags/0"]),
+            vec![Some(&json!("5")), None, Some(&json!("json"))]
+        );
+        assert_eq!(v.pointer_many(&[]), Vec::<Option<&Value>>::new());
+    }
+
+    #[test]
+    fn pointer_add() {
+        let mut v = json!({ "arr": [1, 2], "o": {} });
+
+        assert_eq!(v.pointer_add("/arr/-", json!(3)), Ok(()));
+        assert_eq!(v.pointer("/arr"), Some(&json!([1, 2, 3])));
+        assert_eq!(v.pointer("/arr/-"), None);
+
+        assert_eq!(v.pointer_add("/arr/0", json!(0)), Ok(()));
+        assert_eq!(v.pointer_add("/arr/4", json!(4)), Ok(()));
+        assert_eq!(v.pointer("/arr"), Some(&json!([0, 1, 2, 3, 4])));
+        assert_eq!(v.pointer_add("/o/a~1b", json!(true)), Ok(()));
+        assert_eq!(v.pointer("/o"), Some(&json!({ "a/b": true })));
+
+        assert_eq!(
+            v.pointer_add("/arr/6", json!(null)),
+            Err(PointerError::IndexOutOfRange("6".to_string()))
+        );
+        assert_eq!(
+            v.pointer_add("/missing/-", json!(null)),
+            Err(PointerError::NoSuchKey("missing".to_string()))
+        );
+        assert_eq!(
+            v.pointer_add("/arr/0/x", json!(null)),
+            Err(PointerError::NotAContainer("x".to_string()))
+        );
+        assert_eq!(
+            v.pointer_add("x", json!(null)),
+            Err(PointerError::InvalidPointer)
+        );
+
+        assert_eq!(v.pointer_add("", json!([])), Ok(()));
+        assert_eq!(v, json!([]));
+    }
+
+    #[test]
+    fn try_pointer() {
+        let v = json!({ "a": [1, { "b": true }], "s": "text" });
+
+        assert_eq!(v.try_pointer("/a/1/b"), Ok(&json!(true)));
+        assert_eq!(
+            v.try_pointer("/a/1/c"),
+            Err(PointerError::NoSuchKey("c".to_string()))
+        );
+        assert_eq!(
+            v.try_pointer("/x~1y"),
+            Err(PointerError::NoSuchKey("x/y".to_string()))
+        );
+        assert_eq!(
+            v.try_pointer("/a/2"),
+            Err(PointerError::IndexOutOfRange("2".to_string()))
+        );
+        assert_eq!(
+            v.try_pointer("/a/01"),
+            Err(PointerError::IndexOutOfRange("01".to_string()))
+        );
+        assert_eq!(
+            v.try_pointer("/a/+1"),
+            Err(PointerError::IndexOutOfRange("+1".to_string()))
+        );
+        assert_eq!(
+            v.try_pointer("/a/-"),
+            Err(PointerError::IndexOutOfRange("-".to_string()))
+        );
+        assert_eq!(
+            v.try_pointer("/s/0"),
+            Err(PointerError::NotAContainer("0".to_string()))
+        );
+        assert_eq!(v.try_pointer("a"), Err(PointerError::InvalidPointer));
+        assert_eq!(
+            PointerError::NoSuchKey("c".to_string()).to_string(),
+            "No such key: c"
+        );
+    }
+
+    #[test]
+    fn get_or() {
+        let v = json!({ "host": "localhost", "port": 8080 });
+        let default = json!(null);
+
+        assert_eq!(v.get_or("port", &default), &json!(8080));
+        assert_eq!(v.get_or("user", &default), &default);
+        assert_eq!(json!([1]).get_or("port", &default), &default);
+
+        assert_eq!(v.as_str_or("host", "0.0.0.0"), "localhost");
+        assert_eq!(v.as_str_or("bind", "0.0.0.0"), "0.0.0.0");
+        assert_eq!(v.as_str_or("port", "80"), "80");
+    }
+    #[test]
+    fn get_non_empty_str() {
+        let v = json!({ "host": "localhost", "user": "", "port": 8080 });
+
+        assert_eq!(v.get_non_empty_str("host"), Some("localhost"));
+        assert_eq!(v.get_non_empty_str("user"), None);
+        assert_eq!(v.get_non_empty_str("password"), None);
+        assert_eq!(v.get_non_empty_str("port"), None);
+        assert_eq!(json!([""]).get_non_empty_str("user"), None);
+    }
+    #[test]
+    fn get_ci() {
+        let v = json!({ "name": "rson", "NAME": 1, "Nom": 2, "id": 3 });
+
+        assert_eq!(v.get_ci("name"), Some(&json!("rson")));
+        assert_eq!(v.get_ci("NAME"), Some(&json!(1)));
+        assert_eq!(v.get_ci("Name"), Some(&json!(1)));
+        assert_eq!(v.get_ci("nom"), Some(&json!(2)));
+        assert_eq!(v.get_ci("ID"), Some(&json!(3)));
+        assert_eq!(v.get_ci("version"), None);
+        assert_eq!(json!(["name"]).get_ci("name"), None);
+    }
+
+    #[test]
+    fn contains_key() {
+        let v = json!({ "a": null, "b": { "c": 1 } });
+
+        assert!(v.contains_key("a"));
+        assert!(v.contains_key("b"));
+        assert!(!v.contains_key("c"));
+        assert!(!json!(["a"]).contains_key("a"));
+    }
+
+    #[test]
+    fn keys_and_values() {
+        let v = json!({ "a": 1, "b": [true], "c": { "d": null } });
+
+        let mut keys: Vec<&str> = v.keys().map(|k| &**k).collect();
+        keys.sort();
+        assert_eq!(keys, ["a", "b", "c"]);
+
+        let values: Vec<&Value> = v.values().collect();
+        assert_eq!(values.len(), 3);
+        assert!(values.contains(&&json!(1)));
+        assert!(values.contains(&&json!([true])));
+        assert!(values.contains(&&json!({ "d": null })));
+
+        assert_eq!(json!([1, 2]).keys().count(), 0);
+        assert_eq!(json!("a").values().count(), 0);
+    }
+
+    #[test]
+    fn retain() {
+        let mut v = json!({ "a": 1, "_b": 2, "c": null });
+        v.retain(|k, v| !k.starts_with('_') && *v != Value::Null);
+        assert_eq!(v, json!({ "a": 1 }));
+
+        let mut v = json!([1, "x", 2, null]);
+        v.retain_array(|v| matches!(v, Value::Number(_)));
+        assert_eq!(v, json!([1, 2]));
+
+        let mut v = json!([1]);
+        v.retain(|_, _| false);
+        assert_eq!(v, json!([1]));
+        let mut v = json!({ "a": 1 });
+        v.retain_array(|_| false);
+        assert_eq!(v, json!({ "a": 1 }));
+    }
+    #[test]
+    fn array_contains() {
+        let v = json!([1, "x", [true], { "k": null }]);
+
+        assert!(v.array_contains(&json!(1)));
+        assert!(v.array_contains(&json!("x")));
+        assert!(v.array_contains(&json!([true])));
+        assert!(v.array_contains(&json!({ "k": null })));
+        assert!(!v.array_contains(&json!(2)));
+        assert!(!v.array_contains(&json!(true)));
+        assert!(!json!({ "x": 1 }).array_contains(&json!(1)));
+    }
+    #[test]
+    fn eq_ignore_array_order() {
+        let a = json!({ "tags": ["b", "a", ["y", "x"]], "ids": [1, 2, 2] });
+        let b = json!({ "ids": [2, 1, 2], "tags": [["x", "y"], "a", "b"] });
+
+        assert!(a.eq_ignore_array_order(&b));
+        assert!(b.eq_ignore_array_order(&a));
+        assert_ne!(a, b);
+
+        assert!(!json!([1, 2, 2]).eq_ignore_array_order(&json!([1, 1, 2])));
+        assert!(!json!([1, 2]).eq_ignore_array_order(&json!([2, 1, 1])));
+        assert!(!json!({ "a": [1] }).eq_ignore_array_order(&json!({ "b": [1] })));
+        assert!(!json!([1]).eq_ignore_array_order(&json!({ "0": 1 })));
+        assert!(json!("x").eq_ignore_array_order(&json!("x")));
+    }
+
+    #[test]
+    fn get_range() {
+        let v = match crate::combinator::parse("[0, 1, 2, 3, 4]") {
+            Ok(crate::json::Root::Array(a)) => Value::Array(a),
+            got => panic!("Want Array, got {:?}", got),
+        };
+
+        assert_eq!(v.get_range(1..4), Some(&[json!(1), json!(2), json!(3)][..]));
+        assert_eq!(v.get_range(5..5), Some(&[][..]));
+        assert_eq!(v.get_range(3..6), None);
+        assert_eq!(json!({}).get_range(0..0), None);
+    }
+
+    #[test]
+    fn query() {
+        let v = json!({ "info": { "version": "0.1.0" }, "keywords": ["json", "rust"] });
+
+        assert_eq!(v.query("info.version"), Some(&json!("0.1.0")));
+        assert_eq!(v.query("keywords.1"), Some(&json!("rust")));
+        assert_eq!(v.query("info.name"), None);
+        assert_eq!(v.query("keywords.2"), None);
+        assert_eq!(v.query("keywords.x"), None);
+        assert_eq!(v.query("info.version.major"), None);
+    }
+
+    #[test]
+    fn query_mut() {
+        let mut v = json!({ "info": { "version": "0.1.0" }, "keywords": ["json", "rust"] });
+
+        *v.query_mut("info.version").unwrap() = json!("0.2.0");
+        *v.query_mut("keywords.0").unwrap() = json!("parser");
+
+        assert_eq!(v.query("info.version"), Some(&json!("0.2.0")));
+        assert_eq!(v.query("keywords.0"), Some(&json!("parser")));
+        assert!(v.query_mut("info.name").is_none());
+        assert!(v.query_mut("keywords.2").is_none());
+    }
 }
@@ -0,0 +1,952 @@
+use crate::combinator::{
+    parse_comment, parse_number, parse_str, parse_whitespace, Failure, ParseOptions, Reason,
+};
+use crate::json::Value;
+use nom::{character::complete::multispace1, Err};
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    Comma,
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    Null,
+    /// A run of whitespace, only produced by a lossless lexer.
+    Whitespace(String),
+    /// A comment, delimiters included, only produced by a lossless lexer.
+    Comment(String),
+    /// Input that does not start a valid token, at the given byte offset.
+    Illegal(usize, IllegalKind),
+}
+
+/// Why input was lexed as `Token::Illegal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalKind {
+    /// A character that cannot start any token, such as a stray `\` or `x`.
+    UnexpectedCharacter,
+    /// A control character outside of a string, or unescaped inside one when
+    /// `ParseOptions::strict` is set.
+    ControlCharacter,
+    /// U+FEFF after the start of the document. A leading byte order mark is
+    /// only skipped by `parse_detect_encoding`.
+    ByteOrderMark,
+    MalformedNumber,
+    /// A string containing an invalid `\` escape or lone surrogate.
+    BadEscape,
+    /// A string with no closing quote.
+    UnterminatedString,
+    /// A string over `ParseOptions::max_string_len`.
+    StringTooLong,
+}
+
+/// The kind of a `Token`, without its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    Comma,
+    String,
+    Number,
+    Boolean,
+    Null,
+    Whitespace,
+    Comment,
+    Illegal,
+}
+
+impl Token {
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::LeftBrace => TokenKind::LeftBrace,
+            Token::RightBrace => TokenKind::RightBrace,
+            Token::LeftBracket => TokenKind::LeftBracket,
+            Token::RightBracket => TokenKind::RightBracket,
+            Token::Colon => TokenKind::Colon,
+            Token::Comma => TokenKind::Comma,
+            Token::String(_) => TokenKind::String,
+            Token::Number(_) => TokenKind::Number,
+            Token::Boolean(_) => TokenKind::Boolean,
+            Token::Null => TokenKind::Null,
+            Token::Whitespace(_) => TokenKind::Whitespace,
+            Token::Comment(_) => TokenKind::Comment,
+            Token::Illegal(..) => TokenKind::Illegal,
+        }
+    }
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TokenKind::LeftBrace => write!(f, "`{{`"),
+            TokenKind::RightBrace => write!(f, "`}}`"),
+            TokenKind::LeftBracket => write!(f, "`[`"),
+            TokenKind::RightBracket => write!(f, "`]`"),
+            TokenKind::Colon => write!(f, "`:`"),
+            TokenKind::Comma => write!(f, "`,`"),
+            TokenKind::String => write!(f, "string"),
+            TokenKind::Number => write!(f, "number"),
+            TokenKind::Boolean => write!(f, "boolean"),
+            TokenKind::Null => write!(f, "null"),
+            TokenKind::Whitespace => write!(f, "whitespace"),
+            TokenKind::Comment => write!(f, "comment"),
+            TokenKind::Illegal => write!(f, "illegal input"),
+        }
+    }
+}
+
+pub(crate) const VALUE_KINDS: [TokenKind; 6] = [
+    TokenKind::LeftBrace,
+    TokenKind::LeftBracket,
+    TokenKind::String,
+    TokenKind::Number,
+    TokenKind::Boolean,
+    TokenKind::Null,
+];
+
+/// Kinds of token that may come next at byte `position` of `s`, given the tokens
+/// before it. Meant for diagnostics at `Error::position`; empty once the root
+/// value is complete. `options` should be those `s` was parsed with.
+pub fn expected_tokens(s: &str, position: usize, options: &ParseOptions) -> Vec<TokenKind> {
+    // Open containers, `true` for objects, and what the innermost one waits for.
+    let mut stack = vec![];
+    let mut before = None;
+    let mut after = None;
+    for (token, span) in Lexer::with_options(s, options).spanned() {
+        if span.start >= position {
+            break;
+        }
+        let kind = token.kind();
+        match kind {
+            TokenKind::LeftBrace => stack.push(true),
+            TokenKind::LeftBracket => stack.push(false),
+            TokenKind::RightBrace | TokenKind::RightBracket => {
+                stack.pop();
+            }
+            _ => {}
+        }
+        before = after;
+        after = Some(kind);
+    }
+    let key = before == Some(TokenKind::LeftBrace) || before == Some(TokenKind::Comma);
+
+    let in_object = stack.last().cloned();
+    let mut expected = vec![];
+    match (after, in_object) {
+        (None, _) => expected.extend(&[TokenKind::LeftBrace, TokenKind::LeftBracket]),
+        (Some(TokenKind::LeftBrace), _) => {
+            expected.extend(&[TokenKind::String, TokenKind::RightBrace])
+        }
+        (Some(TokenKind::LeftBracket), _) => {
+            expected.extend(&VALUE_KINDS);
+            expected.push(TokenKind::RightBracket);
+        }
+        (Some(TokenKind::Comma), Some(true)) => {
+            expected.push(TokenKind::String);
+            if options.trailing_commas {
+                expected.push(TokenKind::RightBrace);
+            }
+        }
+        (Some(TokenKind::Comma), _) => {
+            expected.extend(&VALUE_KINDS);
+            if options.trailing_commas {
+                expected.push(TokenKind::RightBracket);
+            }
+        }
+        (Some(TokenKind::Colon), _) => expected.extend(&VALUE_KINDS),
+        (Some(TokenKind::String), Some(true)) if key => expected.push(TokenKind::Colon),
+        (Some(TokenKind::Illegal), _) => {}
+        (Some(_), Some(true)) => expected.extend(&[TokenKind::Comma, TokenKind::RightBrace]),
+        (Some(_), Some(false)) => expected.extend(&[TokenKind::Comma, TokenKind::RightBracket]),
+        (Some(_), None) => {}
+    }
+    expected
+}
+
+/// Byte offsets of a token in the source, `start` inclusive and `end` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+pub struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+    options: Cow<'a, ParseOptions>,
+    lossless: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Lexer<'a> {
+        Lexer {
+            input,
+            pos: 0,
+            options: Cow::Owned(ParseOptions::default()),
+            lossless: false,
+        }
+    }
+
+    pub fn with_options(input: &'a str, options: &'a ParseOptions) -> Lexer<'a> {
+        Lexer {
+            options: Cow::Borrowed(options),
+            ..Lexer::new(input)
+        }
+    }
+
+    /// A lexer that yields whitespace and, when `options.comments` is set,
+    /// comments as tokens instead of skipping them, so that the source can be
+    /// rebuilt exactly from the tokens.
+    pub fn lossless(input: &'a str, options: &'a ParseOptions) -> Lexer<'a> {
+        Lexer {
+            lossless: true,
+            ..Lexer::with_options(input, options)
+        }
+    }
+
+    /// Returns the next token together with its source span.
+    pub fn next_spanned(&mut self) -> Option<(Token, Span)> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let token = self.next_token()?;
+        Some((
+            token,
+            Span {
+                start,
+                end: self.pos,
+            },
+        ))
+    }
+
+    /// Turns the lexer into an iterator of `(Token, Span)` pairs.
+    pub fn spanned(self) -> Spanned<'a> {
+        Spanned { lexer: self }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        if self.lossless {
+            return;
+        }
+        if let Ok((rest, _)) = parse_whitespace(self.rest(), &self.options) {
+            self.advance_to(rest);
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.skip_whitespace();
+        if let Some(token) = self.consume_trivia() {
+            return Some(token);
+        }
+        let token = match self.rest().chars().next()? {
+            '{' => self.consume_char(Token::LeftBrace),
+            '}' => self.consume_char(Token::RightBrace),
+            '[' => self.consume_char(Token::LeftBracket),
+            ']' => self.consume_char(Token::RightBracket),
+            ':' => self.consume_char(Token::Colon),
+            ',' => self.consume_char(Token::Comma),
+            '"' => self.consume_string(),
+            '\'' if self.options.single_quotes => self.consume_string(),
+            '-' | '+' | '0'..='9' => self.consume_number(),
+            _ => self.consume_keyword(),
+        };
+        Some(token)
+    }
+
+    // Whitespace or a comment, which only a lossless lexer has left to consume.
+    fn consume_trivia(&mut self) -> Option<Token> {
+        if !self.lossless {
+            return None;
+        }
+        let input = self.rest();
+        let (rest, token) = if let Ok((rest, ws)) = multispace1::<_, ()>(input) {
+            (rest, Token::Whitespace(ws.to_string()))
+        } else if let (true, Ok((rest, c))) = (self.options.comments, parse_comment(input)) {
+            (rest, Token::Comment(c.to_string()))
+        } else {
+            return None;
+        };
+        self.advance_to(rest);
+        Some(token)
+    }
+
+    fn consume_char(&mut self, token: Token) -> Token {
+        self.pos += 1;
+        token
+    }
+
+    fn consume_string(&mut self) -> Token {
+        match parse_str(self.rest(), &self.options) {
+            Ok((rest, s)) => {
+                self.advance_to(rest);
+                Token::String(s)
+            }
+            Err(Err::Failure(Failure {
+                reason: Reason::StringTooLong,
+                ..
+            })) => self.found_illegal(IllegalKind::StringTooLong),
+            Err(Err::Failure(Failure {
+                reason: Reason::Strict(_),
+                ..
+            })) => self.found_illegal(IllegalKind::ControlCharacter),
+            // Decoding stops at the end of the input or at the offending escape.
+            Err(Err::Error(f)) | Err(Err::Failure(f)) if f.input.is_empty() => {
+                self.found_illegal(IllegalKind::UnterminatedString)
+            }
+            Err(_) => self.found_illegal(IllegalKind::BadEscape),
+        }
+    }
+
+    fn consume_number(&mut self) -> Token {
+        match parse_number(self.rest(), &self.options) {
+            Ok((rest, Value::Number(n))) => {
+                self.advance_to(rest);
+                Token::Number(n)
+            }
+            _ => self.found_illegal(IllegalKind::MalformedNumber),
+        }
+    }
+
+    fn consume_keyword(&mut self) -> Token {
+        for (keyword, token) in &[
+            ("true", Token::Boolean(true)),
+            ("false", Token::Boolean(false)),
+            ("null", Token::Null),
+        ] {
+            if self.rest().starts_with(keyword) {
+                self.pos += keyword.len();
+                return token.clone();
+            }
+        }
+        if self.options.undefined_as_null
+            && !self.options.strict
+            && self.rest().starts_with("undefined")
+        {
+            self.pos += "undefined".len();
+            return Token::Null;
+        }
+        match self.rest().chars().next() {
+            Some(c) if c.is_control() => self.found_illegal(IllegalKind::ControlCharacter),
+            Some('\u{feff}') => self.found_illegal(IllegalKind::ByteOrderMark),
+            _ => self.found_illegal(IllegalKind::UnexpectedCharacter),
+        }
+    }
+
+    // Skips the offending character so that lexing can carry on after it.
+    fn found_illegal(&mut self, kind: IllegalKind) -> Token {
+        let start = self.pos;
+        self.pos += self.rest().chars().next().map_or(0, char::len_utf8);
+        Token::Illegal(start, kind)
+    }
+
+    fn advance_to(&mut self, rest: &str) {
+        self.pos = self.input.len() - rest.len();
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.next_token()
+    }
+}
+
+pub struct Spanned<'a> {
+    lexer: Lexer<'a>,
+}
+
+impl<'a> Iterator for Spanned<'a> {
+    type Item = (Token, Span);
+
+    fn next(&mut self) -> Option<(Token, Span)> {
+        self.lexer.next_spanned()
+    }
+}
+
+/// Outcome of `Tokenizer::next_token`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// A complete token, with its span in the whole stream.
+    Token(Token, Span),
+    /// The buffered input ends inside a token; `feed` more and try again.
+    NeedMore,
+    /// All input was consumed after `finish`.
+    End,
+}
+
+/// Incremental lexer for input that arrives in chunks.
+///
+/// Tokens that could still grow with more input, such as a number or string
+/// at the end of the buffer, are held back with `Step::NeedMore` until more
+/// input is fed or `finish` marks the end of the stream.
+#[derive(Debug, Default)]
+pub struct Tokenizer {
+    buffer: String,
+    // Stream offset of `buffer[0]`.
+    offset: usize,
+    // Start of the input not yet tokenized, in `buffer`.
+    pos: usize,
+    options: ParseOptions,
+    finished: bool,
+}
+
+impl Tokenizer {
+    pub fn new() -> Tokenizer {
+        Tokenizer::default()
+    }
+
+    pub fn with_options(options: &ParseOptions) -> Tokenizer {
+        Tokenizer {
+            options: options.clone(),
+            ..Tokenizer::default()
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        // Drop the consumed prefix once it is at least half the buffer, so
+        // that each byte is moved a bounded number of times.
+        if self.pos >= self.buffer.len() / 2 {
+            self.buffer.drain(..self.pos);
+            self.offset += self.pos;
+            self.pos = 0;
+        }
+        self.buffer.push_str(chunk);
+    }
+
+    /// Marks the end of the stream, so buffered tokens are taken as they are.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    pub fn next_token(&mut self) -> Step {
+        let input = &self.buffer[self.pos..];
+        let (token, span) = match Lexer::with_options(input, &self.options).next_spanned() {
+            Some(t) => t,
+            None if self.finished => return Step::End,
+            // Trailing whitespace may be the start of a comment.
+            None => return Step::NeedMore,
+        };
+        if !self.finished {
+            let rest = &input[span.start..];
+            let more = match token {
+                Token::Number(_) | Token::Boolean(_) | Token::Null => span.end == input.len(),
+                Token::Illegal(..) => self.may_continue(rest),
+                _ => false,
+            };
+            if more {
+                return Step::NeedMore;
+            }
+        }
+
+        let start = self.offset + self.pos + span.start;
+        self.pos += span.end;
+        let token = match token {
+            Token::Illegal(_, kind) => Token::Illegal(start, kind),
+            token => token,
+        };
+        Step::Token(
+            token,
+            Span {
+                start,
+                end: self.offset + self.pos,
+            },
+        )
+    }
+
+    // Whether unlexable `rest` is only the beginning of a token cut off by the buffer end.
+    fn may_continue(&self, rest: &str) -> bool {
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some('"') => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => return false,
+                        _ => {}
+                    }
+                }
+                true
+            }
+            Some('-') | Some('+') | Some('0'..='9') => rest
+                .chars()
+                .all(|c| c.is_ascii_digit() || "+-.eE".contains(c)),
+            Some('/') if self.options.comments => {
+                rest == "/" || (rest.starts_with("/*") && !rest.contains("*/"))
+            }
+            _ => ["true", "false", "null"]
+                .iter()
+                .any(|k| k.starts_with(rest)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combinator::{parse, parse_with_options, Error, ParseOptions};
+    use crate::json::{Root, Value};
+    use crate::lexer::{
+        expected_tokens, IllegalKind, Lexer, Span, Step, Token, TokenKind, Tokenizer,
+    };
+
+    #[test]
+    fn tokens() {
+        let got: Vec<Token> = Lexer::new(" [true, false, null, \"x\\ny\", -1.5e1, {}] ").collect();
+
+        assert_eq!(
+            got,
+            vec![
+                Token::LeftBracket,
+                Token::Boolean(true),
+                Token::Comma,
+                Token::Boolean(false),
+                Token::Comma,
+                Token::Null,
+                Token::Comma,
+                Token::String("x\ny".to_string()),
+                Token::Comma,
+                Token::Number(-15.0),
+                Token::Comma,
+                Token::LeftBrace,
+                Token::RightBrace,
+                Token::RightBracket,
+            ]
+        );
+    }
+    #[test]
+    fn escaped_key_and_value() {
+        let input = r#"{"say \"hi\"": "line\nbreak \"q\""}"#;
+        let got: Vec<(Token, Span)> = Lexer::new(input).spanned().collect();
+
+        assert_eq!(
+            got,
+            vec![
+                (Token::LeftBrace, Span { start: 0, end: 1 }),
+                (
+                    Token::String("say \"hi\"".to_string()),
+                    Span { start: 1, end: 13 }
+                ),
+                (Token::Colon, Span { start: 13, end: 14 }),
+                (
+                    Token::String("line\nbreak \"q\"".to_string()),
+                    Span { start: 15, end: 34 }
+                ),
+                (Token::RightBrace, Span { start: 34, end: 35 }),
+            ]
+        );
+    }
+    #[test]
+    fn expected() {
+        let default = ParseOptions::default();
+        let expected_at_error =
+            |s| expected_tokens(s, parse(s).unwrap_err().position().unwrap(), &default);
+
+        assert_eq!(
+            expected_at_error("[1, }"),
+            vec![
+                TokenKind::LeftBrace,
+                TokenKind::LeftBracket,
+                TokenKind::String,
+                TokenKind::Number,
+                TokenKind::Boolean,
+                TokenKind::Null,
+            ]
+        );
+        assert_eq!(
+            expected_at_error("{\"a\": 1 2}"),
+            vec![TokenKind::Comma, TokenKind::RightBrace]
+        );
+        assert_eq!(expected_at_error("{\"a\" 1}"), vec![TokenKind::Colon]);
+        assert_eq!(
+            expected_at_error("{1}"),
+            vec![TokenKind::String, TokenKind::RightBrace]
+        );
+        assert_eq!(
+            expected_at_error("[[true] x]"),
+            vec![TokenKind::Comma, TokenKind::RightBracket]
+        );
+        assert_eq!(
+            expected_at_error("x"),
+            vec![TokenKind::LeftBrace, TokenKind::LeftBracket]
+        );
+        assert_eq!(expected_tokens("[]", 2, &default), vec![]);
+
+        let message: Vec<String> = expected_tokens("[", 1, &default)
+            .iter()
+            .map(|k| k.to_string())
+            .collect();
+        assert_eq!(
+            message.join(", "),
+            "`{`, `[`, string, number, boolean, null, `]`"
+        );
+    }
+    #[test]
+    fn expected_with_options() {
+        let options = ParseOptions {
+            comments: true,
+            trailing_commas: true,
+            ..ParseOptions::default()
+        };
+        let expected_at_error = |s| {
+            let p = parse_with_options(s, &options)
+                .unwrap_err()
+                .position()
+                .unwrap();
+            expected_tokens(s, p, &options)
+        };
+
+        assert_eq!(
+            expected_at_error("{\"a\" /* x */ 1}"),
+            vec![TokenKind::Colon]
+        );
+        assert_eq!(
+            expected_at_error("[1, // x\n :]"),
+            vec![
+                TokenKind::LeftBrace,
+                TokenKind::LeftBracket,
+                TokenKind::String,
+                TokenKind::Number,
+                TokenKind::Boolean,
+                TokenKind::Null,
+                TokenKind::RightBracket,
+            ]
+        );
+        assert_eq!(
+            expected_at_error("{\"a\": 1, 2}"),
+            vec![TokenKind::String, TokenKind::RightBrace]
+        );
+    }
+    #[test]
+    fn spans() {
+        let got: Vec<(Token, Span)> = Lexer::new("{\"a\":1}").spanned().collect();
+
+        assert_eq!(
+            got,
+            vec![
+                (Token::LeftBrace, Span { start: 0, end: 1 }),
+                (Token::String("a".to_string()), Span { start: 1, end: 4 }),
+                (Token::Colon, Span { start: 4, end: 5 }),
+                (Token::Number(1.0), Span { start: 5, end: 6 }),
+                (Token::RightBrace, Span { start: 6, end: 7 }),
+            ]
+        );
+    }
+    #[test]
+    fn spans_after_whitespace() {
+        let got: Vec<(Token, Span)> = Lexer::new("  [ \"はろー\" ]").spanned().collect();
+
+        assert_eq!(
+            got,
+            vec![
+                (Token::LeftBracket, Span { start: 2, end: 3 }),
+                (
+                    Token::String("はろー".to_string()),
+                    Span { start: 4, end: 15 }
+                ),
+                (Token::RightBracket, Span { start: 16, end: 17 }),
+            ]
+        );
+    }
+    #[test]
+    fn illegal() {
+        let got: Vec<(Token, Span)> = Lexer::new("[x, \"y]").spanned().collect();
+
+        assert_eq!(
+            got,
+            vec![
+                (Token::LeftBracket, Span { start: 0, end: 1 }),
+                (
+                    Token::Illegal(1, IllegalKind::UnexpectedCharacter),
+                    Span { start: 1, end: 2 }
+                ),
+                (Token::Comma, Span { start: 2, end: 3 }),
+                (
+                    Token::Illegal(4, IllegalKind::UnterminatedString),
+                    Span { start: 4, end: 5 }
+                ),
+                (
+                    Token::Illegal(5, IllegalKind::UnexpectedCharacter),
+                    Span { start: 5, end: 6 }
+                ),
+                (Token::RightBracket, Span { start: 6, end: 7 }),
+            ]
+        );
+    }
+    #[test]
+    fn illegal_kind() {
+        let first = |s| Lexer::new(s).next();
+        let kind = |s| match first(s) {
+            Some(Token::Illegal(_, kind)) => kind,
+            got => panic!("Want Illegal, got {:?}", got),
+        };
+
+        assert_eq!(kind(r#""a\qb""#), IllegalKind::BadEscape);
+        assert_eq!(kind(r#""\ud800""#), IllegalKind::BadEscape);
+        assert_eq!(kind(r#""abc"#), IllegalKind::UnterminatedString);
+        assert_eq!(kind(r#""abc\"#), IllegalKind::UnterminatedString);
+        assert_eq!(kind("-x"), IllegalKind::MalformedNumber);
+        assert_eq!(kind("1e999"), IllegalKind::MalformedNumber);
+        assert_eq!(kind("\\"), IllegalKind::UnexpectedCharacter);
+        assert_eq!(kind("nul"), IllegalKind::UnexpectedCharacter);
+        assert_eq!(kind("\u{0}"), IllegalKind::ControlCharacter);
+        assert_eq!(kind("\u{feff}"), IllegalKind::ByteOrderMark);
+
+        let limited = ParseOptions {
+            max_string_len: Some(1),
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            Lexer::with_options(r#""ab""#, &limited).next(),
+            Some(Token::Illegal(0, IllegalKind::StringTooLong))
+        );
+    }
+    #[test]
+    fn malformed_sign() {
+        let lex = |s| Lexer::new(s).spanned().collect::<Vec<_>>();
+
+        assert_eq!(
+            lex("-"),
+            vec![(
+                Token::Illegal(0, IllegalKind::MalformedNumber),
+                Span { start: 0, end: 1 }
+            )]
+        );
+        // Recovery skips one character at a time, so the rest is lexed on its own.
+        assert_eq!(
+            lex("-.5"),
+            vec![
+                (
+                    Token::Illegal(0, IllegalKind::MalformedNumber),
+                    Span { start: 0, end: 1 }
+                ),
+                (
+                    Token::Illegal(1, IllegalKind::UnexpectedCharacter),
+                    Span { start: 1, end: 2 }
+                ),
+                (Token::Number(5.0), Span { start: 2, end: 3 }),
+            ]
+        );
+        assert_eq!(
+            lex("- 5"),
+            vec![
+                (
+                    Token::Illegal(0, IllegalKind::MalformedNumber),
+                    Span { start: 0, end: 1 }
+                ),
+                (Token::Number(5.0), Span { start: 2, end: 3 }),
+            ]
+        );
+
+        assert_eq!(parse("[-]"), Err(Error::ParseError(1)));
+        assert_eq!(parse("[-.5]"), Err(Error::ParseError(1)));
+        assert_eq!(parse("[1, - 5]"), Err(Error::ParseError(4)));
+    }
+    #[test]
+    fn tokenizer_chunks() {
+        let chunks = [
+            "{\"na",
+            "me\": \"rs\\u00",
+            "6fn\", \"v\": [1.",
+            "5e1, tr",
+            "ue, null]}",
+        ];
+        let whole = chunks.concat();
+        let want: Vec<(Token, Span)> = Lexer::new(&whole).spanned().collect();
+
+        let mut tokenizer = Tokenizer::new();
+        let mut got = vec![];
+        // Three feeds, the last of which carries two chunks.
+        for feed in [&chunks[..2], &chunks[2..3], &chunks[3..]].iter() {
+            for chunk in feed.iter() {
+                tokenizer.feed(chunk);
+            }
+            while let Step::Token(token, span) = tokenizer.next_token() {
+                got.push((token, span));
+            }
+        }
+        assert_eq!(tokenizer.next_token(), Step::NeedMore);
+        tokenizer.finish();
+        assert_eq!(tokenizer.next_token(), Step::End);
+        assert_eq!(got, want);
+        assert_eq!(got[3].0, Token::String("rson".to_string()));
+    }
+    #[test]
+    fn tokenizer_compacts_buffer() {
+        let whole = format!("[{}0]", "1, ".repeat(1000));
+        let want: Vec<(Token, Span)> = Lexer::new(&whole).spanned().collect();
+
+        let mut tokenizer = Tokenizer::new();
+        let mut got = vec![];
+        for chunk in whole.as_bytes().chunks(7) {
+            tokenizer.feed(core::str::from_utf8(chunk).unwrap());
+            while let Step::Token(token, span) = tokenizer.next_token() {
+                got.push((token, span));
+            }
+            assert!(tokenizer.buffer.len() < 32, "{}", tokenizer.buffer.len());
+        }
+        tokenizer.finish();
+        while let Step::Token(token, span) = tokenizer.next_token() {
+            got.push((token, span));
+        }
+        assert_eq!(got, want);
+    }
+    #[test]
+    fn tokenizer_need_more() {
+        let mut tokenizer = Tokenizer::with_options(&ParseOptions {
+            comments: true,
+            ..ParseOptions::default()
+        });
+
+        tokenizer.feed("[12");
+        assert_eq!(
+            tokenizer.next_token(),
+            Step::Token(Token::LeftBracket, Span { start: 0, end: 1 })
+        );
+        assert_eq!(tokenizer.next_token(), Step::NeedMore);
+        tokenizer.feed("3 // x");
+        assert_eq!(
+            tokenizer.next_token(),
+            Step::Token(Token::Number(123.0), Span { start: 1, end: 4 })
+        );
+        assert_eq!(tokenizer.next_token(), Step::NeedMore);
+        tokenizer.feed("y\n x");
+        assert_eq!(
+            tokenizer.next_token(),
+            Step::Token(
+                Token::Illegal(12, IllegalKind::UnexpectedCharacter),
+                Span { start: 12, end: 13 }
+            )
+        );
+        tokenizer.feed("\"ab");
+        assert_eq!(tokenizer.next_token(), Step::NeedMore);
+        tokenizer.finish();
+        assert_eq!(
+            tokenizer.next_token(),
+            Step::Token(
+                Token::Illegal(13, IllegalKind::UnterminatedString),
+                Span { start: 13, end: 14 }
+            )
+        );
+    }
+    #[test]
+    fn comments_at_eof() {
+        let options = ParseOptions {
+            comments: true,
+            ..ParseOptions::default()
+        };
+        let want = vec![Token::LeftBracket, Token::Number(1.0), Token::RightBracket];
+
+        for input in &["[1] // tail", "[1]//", "[1] /* tail */"] {
+            assert_eq!(
+                Lexer::with_options(input, &options).collect::<Vec<_>>(),
+                want
+            );
+
+            let mut tokenizer = Tokenizer::with_options(&options);
+            tokenizer.feed(input);
+            tokenizer.finish();
+            let mut got = vec![];
+            while let Step::Token(token, _) = tokenizer.next_token() {
+                got.push(token);
+            }
+            assert_eq!(got, want);
+            assert_eq!(tokenizer.next_token(), Step::End);
+        }
+    }
+    #[test]
+    fn lossless() {
+        let options = ParseOptions {
+            comments: true,
+            ..ParseOptions::default()
+        };
+        let input = "[1, // one\n\t2 /* two */]\n";
+        let got: Vec<_> = Lexer::lossless(input, &options).collect();
+
+        assert_eq!(
+            got,
+            vec![
+                Token::LeftBracket,
+                Token::Number(1.0),
+                Token::Comma,
+                Token::Whitespace(" ".to_string()),
+                Token::Comment("// one".to_string()),
+                Token::Whitespace("\n\t".to_string()),
+                Token::Number(2.0),
+                Token::Whitespace(" ".to_string()),
+                Token::Comment("/* two */".to_string()),
+                Token::RightBracket,
+                Token::Whitespace("\n".to_string()),
+            ]
+        );
+        let spans: Vec<_> = Lexer::lossless(input, &options).spanned().collect();
+        let rebuilt: String = spans.iter().map(|(_, s)| &input[s.start..s.end]).collect();
+        assert_eq!(rebuilt, input);
+
+        assert_eq!(
+            Lexer::lossless(" 1 //", &ParseOptions::default()).collect::<Vec<_>>(),
+            vec![
+                Token::Whitespace(" ".to_string()),
+                Token::Number(1.0),
+                Token::Whitespace(" ".to_string()),
+                Token::Illegal(3, IllegalKind::UnexpectedCharacter),
+                Token::Illegal(4, IllegalKind::UnexpectedCharacter),
+            ]
+        );
+    }
+    #[test]
+    fn exponent_signs() {
+        for &(input, want) in &[("1e+5", 1e5), ("1e-5", 1e-5), ("1e5", 1e5), ("2E+2", 200.0)] {
+            assert_eq!(
+                parse(&format!("[{}]", input)),
+                Ok(Root::Array(vec![Value::Number(want)]))
+            );
+            assert_eq!(
+                Lexer::new(input).collect::<Vec<_>>(),
+                vec![Token::Number(want)]
+            );
+        }
+    }
+    #[test]
+    fn strict_plus_sign() {
+        let strict = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+
+        assert_eq!(
+            Lexer::with_options("+5", &strict).collect::<Vec<_>>(),
+            vec![
+                Token::Illegal(0, IllegalKind::MalformedNumber),
+                Token::Number(5.0)
+            ]
+        );
+        assert_eq!(
+            Lexer::with_options("5e+3 -5", &strict).collect::<Vec<_>>(),
+            vec![Token::Number(5000.0), Token::Number(-5.0)]
+        );
+        assert_eq!(
+            Lexer::new("+5").collect::<Vec<_>>(),
+            vec![Token::Number(5.0)]
+        );
+    }
+}
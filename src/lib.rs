@@ -1,4 +1,26 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(test)]
+#[macro_use]
+mod test_util;
+#[cfg(test)]
+mod prop_test;
+
+#[cfg(feature = "std")]
 pub mod ep;
+pub mod events;
 #[macro_use]
 pub mod json;
+pub mod builder;
 pub mod combinator;
+pub mod comments;
+pub mod convert;
+pub mod lexer;
+pub mod parser;
+pub mod serializer;
+pub mod strict;
+
+pub use crate::combinator::unescape;
+pub use crate::serializer::{escape, minify, prettify};
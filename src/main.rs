@@ -1,15 +1,28 @@
 extern crate rson;
 
+use std::io::Read;
+
 fn main() {
     let input = match try_read_from_stdin::<String>() {
         Ok(x) => x,
         _ => std::process::exit(1),
     };
-    rson::ep::run(input);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let format = if args.iter().any(|a| a == "--pretty") {
+        rson::ep::Format::Pretty
+    } else if args
+        .windows(2)
+        .any(|w| w[0] == "--format" && w[1] == "debug")
+    {
+        rson::ep::Format::Debug
+    } else {
+        rson::ep::Format::Json
+    };
+    rson::ep::run_with_format(&input, format, &mut std::io::stdout()).ok();
 }
 
 fn try_read_from_stdin<T: std::str::FromStr>() -> Result<T, T::Err> {
     let mut s = String::new();
-    std::io::stdin().read_line(&mut s).ok();
+    std::io::stdin().read_to_string(&mut s).ok();
     s.parse()
 }
@@ -0,0 +1,378 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+use crate::combinator::ParseOptions;
+use crate::json::{Array, Object, Root, Value};
+use crate::lexer::{Lexer, Span, Token, TokenKind, VALUE_KINDS};
+
+/// Why the token parser failed, with the byte offset of the offending token.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A token that cannot appear where it is, such as `]` closing an object,
+    /// with the kinds of token that could have come instead. None could after
+    /// the root.
+    FoundUnExpectedToken(Token, usize, Vec<TokenKind>),
+    /// A token where a value should start, such as `,` or `:`.
+    FailedParseValue(Token, usize),
+    /// The tokens end before the root is complete; the offset is the end of
+    /// the last token.
+    UnexpectedEnd(usize),
+}
+
+impl Error {
+    /// Byte offset into the input where the error was detected.
+    pub fn position(&self) -> usize {
+        match self {
+            Error::FoundUnExpectedToken(_, p, _) | Error::FailedParseValue(_, p) => *p,
+            Error::UnexpectedEnd(p) => *p,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::FoundUnExpectedToken(token, p, expected) => {
+                write!(f, "Found unexpected {} at position {}", token.kind(), p)?;
+                match expected.as_slice() {
+                    [] => write!(f, ", expected end of input"),
+                    [kind] => write!(f, ", expected {}", kind),
+                    kinds => {
+                        write!(f, ", expected one of {}", kinds[0])?;
+                        for kind in &kinds[1..] {
+                            write!(f, ", {}", kind)?;
+                        }
+                        Ok(())
+                    }
+                }
+            }
+            Error::FailedParseValue(_, p) => write!(f, "Failed to parse value at position {}", p),
+            Error::UnexpectedEnd(p) => write!(f, "Unexpected end of input at position {}", p),
+        }
+    }
+}
+
+/// Parses `s` with the token parser, for errors that name the offending token
+/// rather than only its offset. The input is lexed with `ParseOptions::strict`,
+/// so a leading `+`, leading zeros and unescaped control characters fail as
+/// `Token::Illegal` values.
+pub fn parse_str_strict(s: &str) -> Result<Root, Error> {
+    let options = ParseOptions::strict();
+    Parser::new(Lexer::with_options(s, &options).spanned()).parse()
+}
+
+/// Builds a `Root` from spanned tokens, such as those of `Lexer::spanned`,
+/// reporting errors in terms of the tokens. Whitespace and comment tokens are
+/// skipped. Containers are kept on an explicit stack, so nesting depth is not
+/// limited by the call stack.
+pub struct Parser<I> {
+    tokens: I,
+    // End of the last token read, where the input is taken to end.
+    end: usize,
+}
+
+// What the innermost open container waits for.
+#[derive(Clone, Copy, PartialEq)]
+enum Expect {
+    FirstKey,
+    Key,
+    Colon,
+    FirstValue,
+    Value,
+    CommaOrClose,
+}
+
+impl Expect {
+    // Kinds of token accepted next in `frame`.
+    fn kinds(self, frame: &Frame) -> Vec<TokenKind> {
+        let mut kinds = vec![];
+        match (self, frame) {
+            (Expect::FirstKey, _) => kinds.extend(&[TokenKind::String, TokenKind::RightBrace]),
+            (Expect::Key, _) => kinds.push(TokenKind::String),
+            (Expect::Colon, _) => kinds.push(TokenKind::Colon),
+            (Expect::FirstValue, _) => {
+                kinds.extend(&VALUE_KINDS);
+                kinds.push(TokenKind::RightBracket);
+            }
+            (Expect::Value, _) => kinds.extend(&VALUE_KINDS),
+            (Expect::CommaOrClose, Frame::Object(..)) => {
+                kinds.extend(&[TokenKind::Comma, TokenKind::RightBrace])
+            }
+            (Expect::CommaOrClose, Frame::Array(_)) => {
+                kinds.extend(&[TokenKind::Comma, TokenKind::RightBracket])
+            }
+        }
+        kinds
+    }
+}
+
+// An open container, with the key of the member being parsed in an object.
+enum Frame {
+    Object(Object, String),
+    Array(Array),
+}
+
+impl<I: Iterator<Item = (Token, Span)>> Parser<I> {
+    pub fn new(tokens: I) -> Parser<I> {
+        Parser { tokens, end: 0 }
+    }
+
+    pub fn parse(mut self) -> Result<Root, Error> {
+        let (mut root, mut expect) = match self.next_token()? {
+            (Token::LeftBrace, _) => (
+                Frame::Object(Object::new(), String::new()),
+                Expect::FirstKey,
+            ),
+            (Token::LeftBracket, _) => (Frame::Array(Array::new()), Expect::FirstValue),
+            (token, start) => {
+                let expected = vec![TokenKind::LeftBrace, TokenKind::LeftBracket];
+                return Err(Error::FoundUnExpectedToken(token, start, expected));
+            }
+        };
+        // Containers open inside `root`, innermost last.
+        let mut stack = vec![];
+        loop {
+            let (token, start) = self.next_token()?;
+            let frame = stack.last_mut().unwrap_or(&mut root);
+            let value = match (expect, token, frame) {
+                (Expect::FirstKey, Token::String(k), Frame::Object(_, key))
+                | (Expect::Key, Token::String(k), Frame::Object(_, key)) => {
+                    *key = k;
+                    expect = Expect::Colon;
+                    continue;
+                }
+                (Expect::Colon, Token::Colon, Frame::Object(..)) => {
+                    expect = Expect::Value;
+                    continue;
+                }
+                (Expect::CommaOrClose, Token::Comma, Frame::Object(..)) => {
+                    expect = Expect::Key;
+                    continue;
+                }
+                (Expect::CommaOrClose, Token::Comma, Frame::Array(_)) => {
+                    expect = Expect::Value;
+                    continue;
+                }
+                (Expect::FirstKey, Token::RightBrace, Frame::Object(..))
+                | (Expect::CommaOrClose, Token::RightBrace, Frame::Object(..))
+                | (Expect::FirstValue, Token::RightBracket, Frame::Array(_))
+                | (Expect::CommaOrClose, Token::RightBracket, Frame::Array(_)) => {
+                    match stack.pop() {
+                        Some(Frame::Object(o, _)) => Value::Object(o),
+                        Some(Frame::Array(a)) => Value::Array(a),
+                        None => return self.finish(root),
+                    }
+                }
+                (Expect::FirstValue, Token::LeftBrace, _)
+                | (Expect::Value, Token::LeftBrace, _) => {
+                    stack.push(Frame::Object(Object::new(), String::new()));
+                    expect = Expect::FirstKey;
+                    continue;
+                }
+                (Expect::FirstValue, Token::LeftBracket, _)
+                | (Expect::Value, Token::LeftBracket, _) => {
+                    stack.push(Frame::Array(Array::new()));
+                    expect = Expect::FirstValue;
+                    continue;
+                }
+                (Expect::FirstValue, token, _) | (Expect::Value, token, _) => match token {
+                    Token::String(s) => Value::String(s),
+                    Token::Number(n) => Value::Number(n),
+                    Token::Boolean(b) => Value::Boolean(b),
+                    Token::Null => Value::Null,
+                    token => return Err(Error::FailedParseValue(token, start)),
+                },
+                (expect, token, frame) => {
+                    let expected = expect.kinds(frame);
+                    return Err(Error::FoundUnExpectedToken(token, start, expected));
+                }
+            };
+            expect = Expect::CommaOrClose;
+            match stack.last_mut().unwrap_or(&mut root) {
+                Frame::Object(o, key) => {
+                    o.insert(core::mem::take(key).into(), value);
+                }
+                Frame::Array(a) => a.push(value),
+            }
+        }
+    }
+
+    // Checks that no tokens follow the root.
+    fn finish(mut self, root: Frame) -> Result<Root, Error> {
+        if let Ok((token, start)) = self.next_token() {
+            return Err(Error::FoundUnExpectedToken(token, start, vec![]));
+        }
+        Ok(match root {
+            Frame::Object(o, _) => Root::Object(o),
+            Frame::Array(a) => Root::Array(a),
+        })
+    }
+
+    // The next token that is not whitespace or a comment, with its offset.
+    fn next_token(&mut self) -> Result<(Token, usize), Error> {
+        loop {
+            match self.tokens.next() {
+                Some((Token::Whitespace(_), span)) | Some((Token::Comment(_), span)) => {
+                    self.end = span.end;
+                }
+                Some((token, span)) => {
+                    self.end = span.end;
+                    return Ok((token, span.start));
+                }
+                None => return Err(Error::UnexpectedEnd(self.end)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combinator::parse;
+    use crate::json::Root;
+    use crate::lexer::TokenKind::{self, *};
+    use crate::lexer::{IllegalKind, Lexer, Token};
+    use crate::parser::{parse_str_strict, Error, Parser};
+
+    fn parse_tokens(s: &str) -> Result<Root, Error> {
+        Parser::new(Lexer::new(s).spanned()).parse()
+    }
+
+    fn unexpected(token: Token, p: usize, expected: &[TokenKind]) -> Result<Root, Error> {
+        Err(Error::FoundUnExpectedToken(token, p, expected.to_vec()))
+    }
+
+    #[test]
+    fn parse_documents() {
+        for s in &[
+            "[]",
+            "{}",
+            r#"{"a": [1, "x", {"b": null}], "c": true}"#,
+            "[[[]], {}, -1.5e3]",
+        ] {
+            assert_eq!(parse_tokens(s).unwrap(), parse(s).unwrap(), "{}", s);
+        }
+    }
+    #[test]
+    fn failed_parse_value() {
+        let err = parse_tokens(r#"{"a": [1, , 2]}"#).unwrap_err();
+        assert_eq!(err, Error::FailedParseValue(Token::Comma, 10));
+        assert_eq!(err.position(), 10);
+        assert_eq!(err.to_string(), "Failed to parse value at position 10");
+        assert_eq!(
+            parse_tokens(r#"{"a": :}"#),
+            Err(Error::FailedParseValue(Token::Colon, 6))
+        );
+    }
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_tokens(r#"{"a": 1]"#),
+            unexpected(Token::RightBracket, 7, &[Comma, RightBrace])
+        );
+        assert_eq!(
+            parse_tokens("[1] 2"),
+            unexpected(Token::Number(2.0), 4, &[])
+        );
+        assert_eq!(
+            parse_tokens("1"),
+            unexpected(Token::Number(1.0), 0, &[LeftBrace, LeftBracket])
+        );
+        assert_eq!(
+            parse_tokens("[1}"),
+            unexpected(Token::RightBrace, 2, &[Comma, RightBracket])
+        );
+        assert_eq!(
+            parse_tokens(r#"[{"a": 1]]"#),
+            unexpected(Token::RightBracket, 8, &[Comma, RightBrace])
+        );
+        assert_eq!(parse_tokens("[1, [2"), Err(Error::UnexpectedEnd(6)));
+        assert_eq!(parse_tokens(" "), Err(Error::UnexpectedEnd(0)));
+    }
+    #[test]
+    fn expected_set() {
+        assert_eq!(
+            parse_tokens(r#"{"a" 1}"#),
+            unexpected(Token::Number(1.0), 5, &[Colon])
+        );
+        assert_eq!(
+            parse_tokens(r#"{"a": 1 2}"#),
+            unexpected(Token::Number(2.0), 8, &[Comma, RightBrace])
+        );
+        assert_eq!(
+            parse_tokens(r#"{1}"#),
+            unexpected(Token::Number(1.0), 1, &[String, RightBrace])
+        );
+        assert_eq!(
+            parse_tokens(r#"{"a": 1, ]"#),
+            unexpected(Token::RightBracket, 9, &[String])
+        );
+
+        let err = parse_tokens(r#"{"a": 1]"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Found unexpected `]` at position 7, expected one of `,`, `}`"
+        );
+        let err = parse_tokens(r#"{"a" 1}"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Found unexpected number at position 5, expected `:`"
+        );
+        let err = parse_tokens("[1] 2").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Found unexpected number at position 4, expected end of input"
+        );
+    }
+    #[test]
+    fn from_str() {
+        assert_eq!(
+            parse_str_strict(r#"{"a": 1, "b" "c"}"#),
+            unexpected(Token::String("c".to_string()), 13, &[Colon])
+        );
+        assert_eq!(
+            parse_str_strict(r#"{"a": tru}"#),
+            Err(Error::FailedParseValue(
+                Token::Illegal(6, IllegalKind::UnexpectedCharacter),
+                6
+            ))
+        );
+        assert_eq!(
+            parse_str_strict(r#" {"a": [true]} "#).map(|r| r.into_value()),
+            Ok(json!({ "a": [true] }))
+        );
+        assert_eq!(
+            parse_str_strict("{\"a\": 1,}"),
+            unexpected(Token::RightBrace, 8, &[String])
+        );
+    }
+    #[test]
+    fn from_str_is_strict() {
+        for (s, at, kind) in &[
+            ("[+5]", 1, IllegalKind::MalformedNumber),
+            ("[01]", 1, IllegalKind::MalformedNumber),
+            ("[\"a\u{1}\"]", 1, IllegalKind::ControlCharacter),
+        ] {
+            assert!(parse(s).is_ok(), "{}", s);
+            assert_eq!(
+                parse_str_strict(s),
+                Err(Error::FailedParseValue(Token::Illegal(*at, *kind), *at)),
+                "{}",
+                s
+            );
+        }
+    }
+    #[test]
+    fn deep_nesting() {
+        let depth = 100_000;
+        let s = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let mut v = parse_tokens(&s).unwrap().into_value();
+        // Take the tree apart level by level; dropping it whole would recurse.
+        while let crate::json::Value::Array(mut a) = v {
+            v = a.pop().unwrap_or(crate::json::Value::Null);
+        }
+    }
+}
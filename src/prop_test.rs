@@ -0,0 +1,127 @@
+// Round-trip property: serializing an arbitrary tree and parsing it back gives
+// the same tree. Trees come from a small seeded generator, so that failures
+// are reproducible from the printed seed without extra dependencies.
+
+use crate::combinator::parse;
+use crate::json::{Array, Object, Root, Value};
+
+// xorshift64*, good enough to spread test cases.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+const EDGE_NUMBERS: [f64; 16] = [
+    0.0,
+    -0.0,
+    1.0,
+    -1.0,
+    0.1,
+    0.30000000000000004,
+    1e-7,
+    1e21,
+    1e100,
+    5e-324,
+    2.2250738585072014e-308,
+    f64::MAX,
+    f64::MIN,
+    9007199254740993.0,
+    -9223372036854775808.0,
+    123456789.125,
+];
+
+const EDGE_CHARS: [char; 16] = [
+    '"', '\\', '/', '\n', '\r', '\t', '\u{08}', '\u{0c}', '\u{0}', '\u{1f}', '\u{7f}', 'é', 'は',
+    '\u{2028}', '😀', '\u{fffd}',
+];
+
+fn number(rng: &mut Rng) -> f64 {
+    match rng.below(3) {
+        0 => *rng.pick(&EDGE_NUMBERS),
+        1 => rng.next() as i64 as f64 / 1000.0,
+        _ => loop {
+            let n = f64::from_bits(rng.next());
+            if n.is_finite() {
+                break n;
+            }
+        },
+    }
+}
+
+fn string(rng: &mut Rng) -> String {
+    (0..rng.below(8))
+        .map(|_| match rng.below(3) {
+            0 => *rng.pick(&EDGE_CHARS),
+            1 => (b' ' + rng.below(95) as u8) as char,
+            _ => loop {
+                if let Some(c) = std::char::from_u32(rng.below(0x11_0000) as u32) {
+                    break c;
+                }
+            },
+        })
+        .collect()
+}
+
+fn array(rng: &mut Rng, depth: usize) -> Array {
+    (0..rng.below(5)).map(|_| value(rng, depth + 1)).collect()
+}
+
+fn object(rng: &mut Rng, depth: usize) -> Object {
+    (0..rng.below(5))
+        .map(|_| (string(rng).into(), value(rng, depth + 1)))
+        .collect()
+}
+
+fn value(rng: &mut Rng, depth: usize) -> Value {
+    // Containers get rarer with depth, which keeps trees finite.
+    let kinds = if depth < 4 { 7 } else { 5 };
+    match rng.below(kinds) {
+        0 => Value::Null,
+        1 => Value::Boolean(rng.below(2) == 0),
+        2 => Value::Number(number(rng)),
+        3 | 4 => Value::String(string(rng)),
+        5 => Value::Array(array(rng, depth)),
+        _ => Value::Object(object(rng, depth)),
+    }
+}
+
+fn root(rng: &mut Rng) -> Root {
+    if rng.below(2) == 0 {
+        Root::Array(array(rng, 0))
+    } else {
+        Root::Object(object(rng, 0))
+    }
+}
+
+#[test]
+fn round_trip() {
+    for seed in 0..2000 {
+        let root = root(&mut Rng::new(seed));
+
+        for json in &[
+            root.to_json_string(),
+            root.to_json_pretty_2space(),
+            root.to_string(),
+        ] {
+            assert_eq!(parse(json).as_ref(), Ok(&root), "seed {}: {}", seed, json);
+        }
+    }
+}
@@ -0,0 +1,730 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+
+use crate::combinator::{parse, parse_with_options, Error, ParseOptions};
+use crate::comments::{parse_with_comments, Comments};
+use crate::json::{push_pointer_token, Array, Key, Object, Root, Value};
+
+impl Root {
+    pub fn to_json_string(&self) -> String {
+        let mut out = String::new();
+        write_root(&mut out, self).unwrap();
+        out
+    }
+
+    /// Pretty serialization with 2-space indentation, `": "` after keys, keys in
+    /// sorted order and a trailing newline, as common formatters write it. With
+    /// the `preserve_order` feature keys are written in the object's order.
+    pub fn to_json_pretty_2space(&self) -> String {
+        self.to_json_pretty(2)
+    }
+
+    /// Like `to_json_pretty_2space`, indenting each level by `indent` spaces.
+    pub fn to_json_pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        write_tree(&mut out, Node::Root(self), Some(indent)).unwrap();
+        out.push('\n');
+        out
+    }
+
+    /// Compact serialization that writes `comments` back next to their values.
+    /// Line comments are followed by a newline so the output stays parseable.
+    pub fn to_json_string_with_comments(&self, comments: &Comments) -> String {
+        let mut out = String::new();
+        let mut path = String::new();
+        write_comments(&mut out, comments.leading(&path)).unwrap();
+        match self {
+            Root::Object(o) => write_object_with_comments(&mut out, o, &mut path, comments),
+            Root::Array(a) => write_array_with_comments(&mut out, a, &mut path, comments),
+        }
+        .unwrap();
+        write_comments(&mut out, comments.trailing(&path)).unwrap();
+        out
+    }
+}
+
+impl Value {
+    pub fn to_json_string(&self) -> String {
+        let mut out = String::new();
+        write_value(&mut out, self).unwrap();
+        out
+    }
+
+    /// See `Root::to_json_pretty_2space`.
+    pub fn to_json_pretty_2space(&self) -> String {
+        self.to_json_pretty(2)
+    }
+
+    /// Length in bytes of `to_json_string`, computed without building the string.
+    pub fn serialized_size_hint(&self) -> usize {
+        let mut size = 0;
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            size += match value {
+                Value::Null => 4,
+                Value::Boolean(true) => 4,
+                Value::Boolean(false) => 5,
+                Value::Number(n) => number_len(*n),
+                Value::String(s) => escaped_len(s) + 2,
+                Value::Array(a) => {
+                    stack.extend(a);
+                    2 + a.len().saturating_sub(1)
+                }
+                Value::Object(o) => {
+                    stack.extend(o.values());
+                    let keys: usize = o.keys().map(|k| escaped_len(k) + 3).sum();
+                    2 + keys + o.len().saturating_sub(1)
+                }
+            };
+        }
+        size
+    }
+
+    /// See `Root::to_json_pretty`.
+    pub fn to_json_pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        write_tree(&mut out, Node::Value(self), Some(indent)).unwrap();
+        out.push('\n');
+        out
+    }
+}
+
+pub(crate) fn write_root<W: Write>(w: &mut W, root: &Root) -> fmt::Result {
+    write_tree(w, Node::Root(root), None)
+}
+
+pub(crate) fn write_value<W: Write>(w: &mut W, value: &Value) -> fmt::Result {
+    write_tree(w, Node::Value(value), None)
+}
+
+fn write_scalar<W: Write>(w: &mut W, value: &Value) -> fmt::Result {
+    match value {
+        Value::Null => w.write_str("null"),
+        Value::Boolean(b) => write!(w, "{}", b),
+        Value::Number(n) => write_number(w, *n),
+        Value::String(s) => write_string(w, s),
+        Value::Object(_) | Value::Array(_) => write_value(w, value),
+    }
+}
+
+// Members are written in the object's own order when it has one: key order for
+// a `BTreeMap`, insertion order for an `IndexMap`. A `HashMap` iterates in an
+// order that differs between runs, so its members are sorted first to keep
+// output deterministic.
+#[cfg(not(any(feature = "btree_map", feature = "preserve_order")))]
+type OrderedMembers<'a> = alloc::vec::IntoIter<(&'a Key, &'a Value)>;
+#[cfg(feature = "btree_map")]
+type OrderedMembers<'a> = alloc::collections::btree_map::Iter<'a, Key, Value>;
+#[cfg(feature = "preserve_order")]
+type OrderedMembers<'a> = indexmap::map::Iter<'a, Key, Value>;
+
+#[cfg(not(any(feature = "btree_map", feature = "preserve_order")))]
+fn ordered_members(object: &Object) -> OrderedMembers<'_> {
+    let mut members: Vec<_> = object.iter().collect();
+    members.sort_by(|a, b| a.0.cmp(b.0));
+    members.into_iter()
+}
+
+#[cfg(any(feature = "btree_map", feature = "preserve_order"))]
+fn ordered_members(object: &Object) -> OrderedMembers<'_> {
+    object.iter()
+}
+
+enum Node<'a> {
+    Root(&'a Root),
+    Value(&'a Value),
+}
+
+enum Members<'a> {
+    Object(OrderedMembers<'a>),
+    Array(core::slice::Iter<'a, Value>),
+}
+
+// An open container and the members left to write in it.
+struct Frame<'a> {
+    members: Members<'a>,
+    first: bool,
+}
+
+// Writes `node` compactly, or pretty-printed with `indent` spaces per level.
+// Open containers are kept on an explicit stack rather than the call stack,
+// so that a tree nested too deeply to recurse into still serializes.
+fn write_tree<W: Write>(w: &mut W, node: Node, indent: Option<usize>) -> fmt::Result {
+    let mut stack = vec![];
+    open(w, node, &mut stack)?;
+    while let Some(frame) = stack.last_mut() {
+        let next = match &mut frame.members {
+            Members::Object(m) => m.next().map(|(k, v)| (Some(k), v)),
+            Members::Array(a) => a.next().map(|v| (None, v)),
+        };
+        let close = match frame.members {
+            Members::Object(_) => '}',
+            Members::Array(_) => ']',
+        };
+        match next {
+            Some((key, v)) => {
+                if !core::mem::replace(&mut frame.first, false) {
+                    w.write_char(',')?;
+                }
+                if let Some(indent) = indent {
+                    write_indent(w, stack.len(), indent)?;
+                }
+                if let Some(k) = key {
+                    write_string(w, k)?;
+                    w.write_str(if indent.is_some() { ": " } else { ":" })?;
+                }
+                open(w, Node::Value(v), &mut stack)?;
+            }
+            None => {
+                stack.pop();
+                if let Some(indent) = indent {
+                    write_indent(w, stack.len(), indent)?;
+                }
+                w.write_char(close)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Writes a scalar whole, or the opening bracket of a container and pushes it.
+// Empty containers are written whole, as `{}` and `[]`.
+fn open<'a, W: Write>(w: &mut W, node: Node<'a>, stack: &mut Vec<Frame<'a>>) -> fmt::Result {
+    let members = match node {
+        Node::Root(Root::Object(o)) | Node::Value(Value::Object(o)) => {
+            if o.is_empty() {
+                return w.write_str("{}");
+            }
+            w.write_char('{')?;
+            Members::Object(ordered_members(o))
+        }
+        Node::Root(Root::Array(a)) | Node::Value(Value::Array(a)) => {
+            if a.is_empty() {
+                return w.write_str("[]");
+            }
+            w.write_char('[')?;
+            Members::Array(a.iter())
+        }
+        Node::Value(v) => return write_scalar(w, v),
+    };
+    stack.push(Frame {
+        members,
+        first: true,
+    });
+    Ok(())
+}
+
+fn write_indent<W: Write>(w: &mut W, depth: usize, indent: usize) -> fmt::Result {
+    w.write_char('\n')?;
+    for _ in 0..depth * indent {
+        w.write_char(' ')?;
+    }
+    Ok(())
+}
+
+fn write_value_with_comments<W: Write>(
+    w: &mut W,
+    value: &Value,
+    path: &mut String,
+    comments: &Comments,
+) -> fmt::Result {
+    match value {
+        Value::Object(o) => write_object_with_comments(w, o, path, comments),
+        Value::Array(a) => write_array_with_comments(w, a, path, comments),
+        v => write_scalar(w, v),
+    }
+}
+
+fn write_object_with_comments<W: Write>(
+    w: &mut W,
+    object: &Object,
+    path: &mut String,
+    comments: &Comments,
+) -> fmt::Result {
+    w.write_char('{')?;
+    for (i, (k, v)) in ordered_members(object).enumerate() {
+        let len = path.len();
+        push_pointer_token(path, k);
+        write_separator_with_comments(w, i, comments.leading(path))?;
+        write_comments(w, comments.leading(path))?;
+        write_string(w, k)?;
+        w.write_char(':')?;
+        write_value_with_comments(w, v, path, comments)?;
+        write_comments(w, comments.trailing(path))?;
+        path.truncate(len);
+    }
+    w.write_char('}')
+}
+
+fn write_array_with_comments<W: Write>(
+    w: &mut W,
+    array: &Array,
+    path: &mut String,
+    comments: &Comments,
+) -> fmt::Result {
+    w.write_char('[')?;
+    for (i, v) in array.iter().enumerate() {
+        let len = path.len();
+        push_pointer_token(path, &i.to_string());
+        write_separator_with_comments(w, i, comments.leading(path))?;
+        write_comments(w, comments.leading(path))?;
+        write_value_with_comments(w, v, path, comments)?;
+        write_comments(w, comments.trailing(path))?;
+        path.truncate(len);
+    }
+    w.write_char(']')
+}
+
+// Leading comments go on a new line after the comma; on the comma's line they
+// would be read back as trailing the previous value.
+fn write_separator_with_comments<W: Write>(w: &mut W, i: usize, leading: &[String]) -> fmt::Result {
+    if i > 0 {
+        w.write_char(',')?;
+        if !leading.is_empty() {
+            w.write_char('\n')?;
+        }
+    }
+    Ok(())
+}
+
+fn write_comments<W: Write>(w: &mut W, comments: &[String]) -> fmt::Result {
+    for c in comments {
+        w.write_str(c)?;
+        if c.starts_with("//") {
+            w.write_char('\n')?;
+        }
+    }
+    Ok(())
+}
+
+// JSON has no representation for NaN or the infinities, so they are written as null.
+// Negative zero is written as `0`, matching how `-0.0 == 0.0` compares, so that
+// equal values always serialize identically.
+//
+// Both `{}` and `{:e}` print the shortest digits that round-trip, without
+// allocating. Like JavaScript, plain notation is used for decimal exponents in
+// -7 < e < 21 and scientific notation otherwise, so `1e100` is not written out
+// as 101 digits. Rust's formatting ignores the locale, so the decimal separator
+// is always `.`.
+fn write_number<W: Write>(w: &mut W, n: f64) -> fmt::Result {
+    if n == 0.0 {
+        return w.write_char('0');
+    } else if !n.is_finite() {
+        return w.write_str("null");
+    }
+    let mut exp = NumberBuf::default();
+    write!(exp, "{:e}", n)?;
+    let s = exp.as_str();
+    let e: i32 = s.find('e').map_or(0, |i| s[i + 1..].parse().unwrap_or(0));
+    if -7 < e && e < 21 {
+        write!(w, "{}", n)
+    } else {
+        w.write_str(s)
+    }
+}
+
+// Length of `n` as `write_number` writes it.
+fn number_len(n: f64) -> usize {
+    let mut len = ByteCount(0);
+    write_number(&mut len, n).unwrap();
+    len.0
+}
+
+struct ByteCount(usize);
+
+impl Write for ByteCount {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+// Stack buffer for the scientific notation of an `f64`, at most 24 bytes as in
+// `-2.2250738585072014e-308`.
+#[derive(Default)]
+struct NumberBuf {
+    buf: [u8; 32],
+    len: usize,
+}
+
+impl NumberBuf {
+    fn as_str(&self) -> &str {
+        // Only whole `&str`s are written.
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl Write for NumberBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Re-serializes the JSON document `s` compactly, without insignificant whitespace.
+pub fn minify(s: &str) -> Result<String, Error> {
+    parse(s).map(|root| root.to_json_string())
+}
+
+/// Like `minify`, parsing with `options`, e.g. to strip comments.
+pub fn minify_with_options(s: &str, options: &ParseOptions) -> Result<String, Error> {
+    parse_with_options(s, options).map(|root| root.to_json_string())
+}
+
+/// Like `minify_with_options` with comments enabled, writing the comments back
+/// next to their values instead of dropping them. See
+/// `Root::to_json_string_with_comments`.
+pub fn minify_with_comments(s: &str, options: &ParseOptions) -> Result<String, Error> {
+    parse_with_comments(s, options)
+        .map(|(root, comments)| root.to_json_string_with_comments(&comments))
+}
+
+/// Re-serializes the JSON document `s` pretty-printed, indenting each level by
+/// `indent` spaces. See `Root::to_json_pretty_2space` for the layout.
+pub fn prettify(s: &str, indent: usize) -> Result<String, Error> {
+    parse(s).map(|root| root.to_json_pretty(indent))
+}
+
+/// Escapes `s` for use as the body of a JSON string, without the surrounding quotes.
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    write_escaped(&mut out, s).unwrap();
+    out
+}
+
+fn write_string<W: Write>(w: &mut W, s: &str) -> fmt::Result {
+    w.write_char('"')?;
+    write_escaped(w, s)?;
+    w.write_char('"')
+}
+
+// Length of `s` as `write_escaped` writes it.
+fn escaped_len(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c {
+            '"' | '\\' | '\n' | '\r' | '\t' | '\u{08}' | '\u{0c}' => 2,
+            c if c < ' ' => 6,
+            c => c.len_utf8(),
+        })
+        .sum()
+}
+
+fn write_escaped<W: Write>(w: &mut W, s: &str) -> fmt::Result {
+    for c in s.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\t' => w.write_str("\\t")?,
+            '\u{08}' => w.write_str("\\b")?,
+            '\u{0c}' => w.write_str("\\f")?,
+            c if c < ' ' => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combinator::{parse, Error, ParseOptions};
+    use crate::json::{Root, Value};
+    use crate::serializer::minify_with_options;
+
+    #[test]
+    fn scalar() {
+        assert_eq!(json!(null).to_json_string(), "null");
+        assert_eq!(json!(true).to_json_string(), "true");
+        assert_eq!(json!(1).to_json_string(), "1");
+        assert_eq!(Value::Number(-1.5).to_json_string(), "-1.5");
+        assert_eq!(Value::Number(f64::NAN).to_json_string(), "null");
+        assert_eq!(json!("json").to_json_string(), "\"json\"");
+    }
+    #[test]
+    fn minify() {
+        let pretty = include_str!("../tests/fixtures/pretty_2space.json");
+        let compact = crate::minify(pretty).unwrap();
+
+        assert!(!compact.contains(&[' ', '\n'][..]));
+        assert_eq!(parse(&compact), parse(pretty));
+        let want = if cfg!(feature = "preserve_order") {
+            r#"{"b":[1,2],"a":{}}"#
+        } else {
+            r#"{"a":{},"b":[1,2]}"#
+        };
+        assert_eq!(
+            crate::minify("{\n  \"b\": [1, 2],\n  \"a\": {}\n}\n"),
+            Ok(want.to_string())
+        );
+
+        let commented = "[1, // one\n 2 /* two */]";
+        assert!(crate::minify(commented).is_err());
+        let options = ParseOptions {
+            comments: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            minify_with_options(commented, &options),
+            Ok("[1,2]".to_string())
+        );
+    }
+    #[test]
+    fn minify_with_comments() {
+        let jsonc = "// Copyright rson authors\n{\n  /* name */ \"name\": \"rson\",\n  \"tags\": [\n    \"json\", // first\n    \"parser\"\n  ]\n}\n";
+        let minify_with_comments = crate::serializer::minify_with_comments;
+        let minified = minify_with_comments(jsonc, &ParseOptions::default()).unwrap();
+
+        assert_eq!(
+            minified,
+            "// Copyright rson authors\n{/* name */\"name\":\"rson\",\"tags\":[\"json\"// first\n,\"parser\"]}"
+        );
+        assert!(!minified.contains("  "));
+        let options = ParseOptions {
+            comments: true,
+            ..ParseOptions::default()
+        };
+        assert!(crate::minify(&minified).is_err());
+        assert_eq!(
+            minify_with_options(&minified, &options),
+            minify_with_options(jsonc, &options)
+        );
+        assert_eq!(
+            minify_with_comments("[1,", &ParseOptions::default()),
+            Err(Error::UnclosedContainer(0))
+        );
+    }
+    #[test]
+    fn prettify() {
+        let compact = r#"{"b":[1,{"c":null}],"a":"x y","d":{},"e":1e100}"#;
+        let pretty = crate::prettify(compact, 4).unwrap();
+
+        // With `preserve_order` members stay in document order instead.
+        #[cfg(not(feature = "preserve_order"))]
+        assert_eq!(
+            pretty,
+            "{\n    \"a\": \"x y\",\n    \"b\": [\n        1,\n        {\n            \"c\": null\n        }\n    ],\n    \"d\": {},\n    \"e\": 1e100\n}\n"
+        );
+        assert_eq!(parse(&pretty), parse(compact));
+        assert_eq!(
+            crate::minify(&pretty).unwrap(),
+            crate::minify(compact).unwrap()
+        );
+        assert_eq!(
+            crate::prettify(compact, 2).unwrap(),
+            parse(compact).unwrap().to_json_pretty_2space()
+        );
+        assert_eq!(crate::prettify("[1,", 2), Err(Error::UnclosedContainer(0)));
+    }
+    #[cfg(not(feature = "preserve_order"))]
+    #[test]
+    fn sorted_keys() {
+        let v = json!({ "b": 1, "a": { "d": [], "c": null }, "B": 2, "aa": 3 });
+        let first = v.to_json_string();
+
+        assert_eq!(first, r#"{"B":2,"a":{"c":null,"d":[]},"aa":3,"b":1}"#);
+        assert_eq!(v.clone().to_json_string(), first);
+        assert_eq!(v.to_string(), first);
+    }
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn document_order() {
+        let v = parse(r#"{"b": 1, "a": {"d": [], "c": null}, "B": 2}"#)
+            .unwrap()
+            .into_value();
+
+        assert_eq!(v.to_json_string(), r#"{"b":1,"a":{"d":[],"c":null},"B":2}"#);
+        assert_eq!(
+            v.to_json_pretty_2space(),
+            "{\n  \"b\": 1,\n  \"a\": {\n    \"d\": [],\n    \"c\": null\n  },\n  \"B\": 2\n}\n"
+        );
+    }
+    #[test]
+    fn shortest_number() {
+        let cases = [
+            (0.1, "0.1"),
+            (1e100, "1e100"),
+            (-1e100, "-1e100"),
+            (1.5e300, "1.5e300"),
+            (5e-324, "5e-324"),
+            (1e-7, "1e-7"),
+            (1e-6, "0.000001"),
+            (123456789012.0, "123456789012"),
+            (1e20, "100000000000000000000"),
+            (1e21, "1e21"),
+            (0.30000000000000004, "0.30000000000000004"),
+            (f64::MAX, "1.7976931348623157e308"),
+        ];
+        for &(n, want) in cases.iter() {
+            let got = Value::Number(n).to_json_string();
+            assert_eq!(got, want);
+            assert_eq!(got.parse::<f64>(), Ok(n));
+        }
+    }
+    #[test]
+    fn negative_zero() {
+        let root = parse("[-0, 0, -0.0]").unwrap();
+        let zeros = match &root {
+            Root::Array(a) => a.clone(),
+            got => panic!("Want Array, got {:?}", got),
+        };
+
+        match zeros[0] {
+            Value::Number(n) => assert!(n == 0.0 && n.is_sign_negative()),
+            ref got => panic!("Want Number, got {:?}", got),
+        }
+        assert_eq!(zeros[0], zeros[1]);
+        assert_eq!(zeros[0], Value::Number(0.0));
+        assert_eq!(root.to_json_string(), "[0,0,0]");
+        assert_eq!(Value::Number(-0.0).to_json_string(), "0");
+    }
+    #[test]
+    fn string_escape() {
+        assert_eq!(
+            json!("a\"b\\c\nd\te\u{1}").to_json_string(),
+            r#""a\"b\\c\nd\te\u0001""#
+        );
+        assert_eq!(json!("はろー").to_json_string(), "\"はろー\"");
+    }
+    #[test]
+    fn key_escape() {
+        let key = "a\"b\\c\nは";
+        let v = json!({ key: 1 });
+        let json = v.to_json_string();
+
+        assert_eq!(json, r#"{"a\"b\\c\nは":1}"#);
+        match parse(&json) {
+            Ok(Root::Object(o)) => assert_eq!(Value::Object(o), v),
+            got => panic!("Want Object, got {:?}", got),
+        }
+    }
+    #[test]
+    fn container() {
+        assert_eq!(json!([]).to_json_string(), "[]");
+        assert_eq!(
+            json!([1, "x", [null, false]]).to_json_string(),
+            r#"[1,"x",[null,false]]"#
+        );
+        assert_eq!(
+            json!({ "a": { "b": [true] } }).to_json_string(),
+            r#"{"a":{"b":[true]}}"#
+        );
+        assert_eq!(
+            Root::Array(vec![json!(1), json!({ "x": null })]).to_json_string(),
+            r#"[1,{"x":null}]"#
+        );
+    }
+    #[test]
+    fn debug() {
+        let v = json!({ "a": ["b", 1, null] });
+        assert_eq!(format!("{:?}", v), r#"{"a":["b",1,null]}"#);
+        assert_eq!(format!("{:?}", v), v.to_json_string());
+        assert_eq!(format!("{:?}", Root::Array(vec![json!(true)])), "[true]");
+    }
+    #[test]
+    fn round_trip() {
+        let documents = [
+            "[]",
+            "{}",
+            r#"[null, true, false, "", 0, -0, 1, -1]"#,
+            r#"[0.5, -1.25, 1e-7, 1.5E+10, 123456789012, 1e300, 5e-324, 0.1, 2.2250738585072014e-308]"#,
+            r#"["\"\\\/\b\f\n\r\t", "\u0000\u001f\u007f", "a\u0041\u00e9"]"#,
+            r#"["はろー", "\u3042\ud83d\ude00", "😀", "e\u0301", "\u2028\u2029"]"#,
+            r#"{"": "", "\"": "\\", "は": {"ネスト": [[[]], {}]}}"#,
+            r#"{"a": [1, {"b": [2, {"c": [3, null]}]}], "d": {"e": {"f": {}}}}"#,
+            r#"[[[[[[[[[["deep"]]]]]]]]]]"#,
+        ];
+
+        for input in documents.iter() {
+            let root = parse(input).unwrap();
+            let json = root.to_json_string();
+
+            assert_eq!(parse(&json), Ok(root.clone()), "{} -> {}", input, json);
+            assert_eq!(parse(&root.to_string()), Ok(root.clone()));
+            assert_eq!(parse(&json).unwrap().to_json_string().len(), json.len());
+        }
+    }
+    #[test]
+    fn serialized_size_hint() {
+        let v = json!({
+            "name": "rson \"json\"\n\u{1}",
+            "キー": [true, false, null, [], {}],
+            "n": [1, -0.5, 1e100],
+            "": { "a": [{ "b": "" }] }
+        });
+        assert_eq!(v.serialized_size_hint(), v.to_json_string().len());
+        assert_eq!(json!("").serialized_size_hint(), 2);
+
+        // Plain notation for small exponents is longer than any scientific one.
+        let extremes = [
+            -1.2345678901234567e-6,
+            -1.2345678901234567e20,
+            -2.2250738585072014e-308,
+            f64::MIN,
+            5e-324,
+            -0.0,
+            f64::NAN,
+            f64::NEG_INFINITY,
+        ];
+        for &n in extremes.iter() {
+            let v = Value::Number(n);
+            assert_eq!(v.serialized_size_hint(), v.to_json_string().len(), "{}", n);
+        }
+        assert_eq!(json!(-1.2345678901234567e-6).serialized_size_hint(), 25);
+    }
+    #[test]
+    fn deep_nesting() {
+        let depth = 100_000;
+        let mut v = json!(1);
+        for _ in 0..depth {
+            v = Value::Array(vec![v]);
+        }
+        let mut o = crate::json::Object::new();
+        o.insert("a".into(), v);
+        let mut root = Root::Object(o);
+
+        let json = root.to_json_string();
+        assert_eq!(json.len(), 2 * depth + 7);
+        assert!(json.starts_with(r#"{"a":[[["#) && json.ends_with("]]]}"));
+        assert_eq!(json.find('1'), Some(5 + depth));
+        assert_eq!(root.to_string(), json);
+        let pretty = root.to_json_pretty(0);
+        assert_eq!(pretty.matches('\n').count(), 2 * depth + 3);
+
+        // Dropping is recursive too, so the tree is taken apart level by level.
+        if let Root::Object(o) = &mut root {
+            let mut v = o.remove("a").unwrap();
+            while let Value::Array(mut a) = v {
+                v = a.pop().unwrap();
+            }
+        }
+    }
+    #[test]
+    fn pretty_2space() {
+        let root = parse(
+            r#"{"name": "rson", "version": 1.5, "tags": ["json", "parser"],
+                "deps": {"nom": {"version": "5", "optional": false}},
+                "empty": {"list": [], "map": {}}, "nothing": null, "escaped": "a\"b\n"}"#,
+        )
+        .unwrap();
+
+        let pretty = root.to_json_pretty_2space();
+        #[cfg(not(feature = "preserve_order"))]
+        assert_eq!(pretty, include_str!("../tests/fixtures/pretty_2space.json"));
+        assert_eq!(parse(&pretty), Ok(root));
+        assert_eq!(Root::Array(vec![]).to_json_pretty_2space(), "[]\n");
+        assert_eq!(
+            json!([1, [2]]).to_json_pretty_2space(),
+            "[\n  1,\n  [\n    2\n  ]\n]\n"
+        );
+        assert_eq!(json!("x").to_json_pretty_2space(), "\"x\"\n");
+    }
+}
@@ -0,0 +1,236 @@
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::combinator::{parse_with_options, Error, ParseOptions};
+use crate::lexer::{IllegalKind, Lexer, Token};
+
+/// Checks that `s` is strict RFC 8259 JSON and reports every violation found.
+///
+/// Comments, trailing commas, leading zeros or `+` signs, numbers that overflow
+/// to infinity, `NaN`/`Infinity`, duplicate object keys and unescaped control
+/// characters in strings are each reported as an `Error::StrictViolation`.
+/// When none of those are present, any remaining syntax error is reported the
+/// same way `parse` would.
+pub fn validate_strict(s: &str) -> Result<(), Vec<Error>> {
+    let options = ParseOptions {
+        comments: true,
+        ..ParseOptions::default()
+    };
+    let mut v = Validator::default();
+    let mut end = 0;
+    for (token, span) in Lexer::with_options(s, &options).spanned() {
+        if span.start < v.skip_to {
+            end = span.end;
+            continue;
+        }
+        v.gap(&s[end..span.start], end);
+        v.token(token, &s[span.start..span.end], span.start, s);
+        end = span.end;
+    }
+    v.gap(&s[end..], end);
+
+    if v.errors.is_empty() {
+        let strict = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+        if let Err(e) = parse_with_options(s, &strict) {
+            v.errors.push(e);
+        }
+    }
+    if v.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(v.errors)
+    }
+}
+
+enum Frame {
+    Array,
+    Object {
+        keys: BTreeSet<String>,
+        expects_key: bool,
+    },
+}
+
+#[derive(Default)]
+struct Validator {
+    errors: Vec<Error>,
+    stack: Vec<Frame>,
+    last_comma: Option<usize>,
+    // Tokens starting before this offset belong to input already reported.
+    skip_to: usize,
+}
+
+impl Validator {
+    // The lexer skips comments as whitespace, so anything else in a gap is one.
+    fn gap(&mut self, gap: &str, offset: usize) {
+        if let Some(i) = gap.find(|c: char| !c.is_whitespace()) {
+            self.violation(offset + i, "comment");
+        }
+    }
+
+    fn token(&mut self, token: Token, raw: &str, start: usize, s: &str) {
+        let comma = self.last_comma.take();
+        if let Token::String(_) = token {
+            for (i, _) in raw.char_indices().filter(|&(_, c)| c < ' ') {
+                self.violation(start + i, "control character in string");
+            }
+        }
+        match token {
+            Token::String(key) if self.expects_key() => {
+                if let Some(Frame::Object { keys, expects_key }) = self.stack.last_mut() {
+                    *expects_key = false;
+                    if !keys.insert(key) {
+                        self.violation(start, "duplicate key");
+                    }
+                }
+            }
+            Token::LeftBrace => self.stack.push(Frame::Object {
+                keys: BTreeSet::new(),
+                expects_key: true,
+            }),
+            Token::LeftBracket => self.stack.push(Frame::Array),
+            Token::RightBrace | Token::RightBracket => {
+                if let Some(p) = comma {
+                    self.violation(p, "trailing comma");
+                }
+                self.stack.pop();
+            }
+            Token::Comma => {
+                if let Some(Frame::Object { expects_key, .. }) = self.stack.last_mut() {
+                    *expects_key = true;
+                }
+                self.last_comma = Some(start);
+            }
+            Token::Number(_) => {
+                let digits = raw.trim_start_matches(&['-', '+'][..]);
+                if raw.starts_with('+') {
+                    self.violation(start, "leading plus sign");
+                }
+                if digits.len() > 1
+                    && digits.starts_with('0')
+                    && digits.as_bytes()[1].is_ascii_digit()
+                {
+                    self.violation(start, "leading zero");
+                }
+            }
+            Token::Illegal(p, IllegalKind::ByteOrderMark) => self.violation(p, "byte order mark"),
+            Token::Illegal(p, _) => self.illegal(p, s),
+            Token::String(_)
+            | Token::Boolean(_)
+            | Token::Null
+            | Token::Colon
+            | Token::Whitespace(_)
+            | Token::Comment(_) => {}
+        }
+    }
+
+    // Reports a run of unlexable input once, naming non-finite numbers.
+    fn illegal(&mut self, p: usize, s: &str) {
+        let rest = &s[p..];
+        let first = rest.chars().next().map_or(0, char::len_utf8);
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+            .unwrap_or(rest.len());
+        let run = &rest[..len.max(first)];
+        let word = run.trim_start_matches(&['-', '+'][..]);
+        let non_finite = word == "NaN"
+            || word == "Infinity"
+            || matches!(run.parse::<f64>(), Ok(n) if n.is_infinite());
+
+        if non_finite {
+            self.violation(p, "non-finite number");
+        } else {
+            self.errors.push(Error::ParseError(p));
+        }
+        self.skip_to = p + run.len();
+    }
+
+    fn expects_key(&self) -> bool {
+        match self.stack.last() {
+            Some(Frame::Object { expects_key, .. }) => *expects_key,
+            _ => false,
+        }
+    }
+
+    fn violation(&mut self, p: usize, reason: &'static str) {
+        self.errors.push(Error::StrictViolation(p, reason));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combinator::Error;
+    use crate::strict::validate_strict;
+
+    #[test]
+    fn valid() {
+        assert_eq!(
+            validate_strict(r#"{"a": [1, -0.5, 1e10], "b": {"a": 0}}"#),
+            Ok(())
+        );
+        assert_eq!(validate_strict("[]"), Ok(()));
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let input = r#"{
+  // comment
+  "a": [01, +2, 1e999, NaN,],
+  "a": -Infinity, /* block */
+  "b": {"c": 1, "c": 2},
+}"#;
+        let at = |needle: &str| input.find(needle).unwrap();
+
+        assert_eq!(
+            validate_strict(input),
+            Err(vec![
+                Error::StrictViolation(at("//"), "comment"),
+                Error::StrictViolation(at("01"), "leading zero"),
+                Error::StrictViolation(at("+2"), "leading plus sign"),
+                Error::StrictViolation(at("1e999"), "non-finite number"),
+                Error::StrictViolation(at("NaN"), "non-finite number"),
+                Error::StrictViolation(at("NaN,") + 3, "trailing comma"),
+                Error::StrictViolation(at("\"a\": -"), "duplicate key"),
+                Error::StrictViolation(at("-Inf"), "non-finite number"),
+                Error::StrictViolation(at("/*"), "comment"),
+                Error::StrictViolation(at("\"c\": 2"), "duplicate key"),
+                Error::StrictViolation(at("},\n}") + 1, "trailing comma"),
+            ])
+        );
+    }
+
+    #[test]
+    fn control_characters() {
+        assert_eq!(
+            validate_strict("[\"a\nb\", {\"k\\t\u{0}\": \"\t\"}]"),
+            Err(vec![
+                Error::StrictViolation(3, "control character in string"),
+                Error::StrictViolation(13, "control character in string"),
+                Error::StrictViolation(18, "control character in string"),
+            ])
+        );
+        assert_eq!(validate_strict(r#"["a\nb\u0000"]"#), Ok(()));
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        assert_eq!(
+            validate_strict("[1,\u{feff} 2]"),
+            Err(vec![Error::StrictViolation(3, "byte order mark")])
+        );
+    }
+
+    #[test]
+    fn syntax_error() {
+        assert_eq!(validate_strict("[1 2]"), Err(vec![Error::ParseError(3)]));
+        assert_eq!(validate_strict("[x]"), Err(vec![Error::ParseError(1)]));
+        assert_eq!(validate_strict("[é]"), Err(vec![Error::ParseError(1)]));
+        assert_eq!(
+            validate_strict("[1] 2"),
+            Err(vec![Error::FoundUnTerminatedError(4)])
+        );
+    }
+}
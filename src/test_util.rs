@@ -0,0 +1,13 @@
+// Helpers shared by the test modules.
+
+macro_rules! hash {
+    ( $( $t:expr),* ) => {
+        {
+            let mut temp_hash = $crate::json::Object::new();
+            $(
+                temp_hash.insert($t.0.into(), $t.1);
+            )*
+            temp_hash
+        }
+    };
+}
@@ -0,0 +1,76 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use rson::combinator::parse;
+use rson::json::Value;
+
+fn rson(args: &[&str], input: &str) -> String {
+    rson_with_env(args, &[], input)
+}
+
+fn rson_with_env(args: &[&str], env: &[(&str, &str)], input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rson"))
+        .args(args)
+        .envs(env.iter().cloned())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn pretty() {
+    let input = "{\"b\": [1, {\"c\": null}],\n \"a\": \"x\"}\n";
+
+    let want = if cfg!(feature = "preserve_order") {
+        "{\n  \"b\": [\n    1,\n    {\n      \"c\": null\n    }\n  ],\n  \"a\": \"x\"\n}\n"
+    } else {
+        "{\n  \"a\": \"x\",\n  \"b\": [\n    1,\n    {\n      \"c\": null\n    }\n  ]\n}\n"
+    };
+    assert_eq!(rson(&["--pretty"], input), want);
+    assert_eq!(
+        rson(&["--pretty"], "[1,x]"),
+        "{\"error\":\"Failed to parse value at byte 3\",\"position\":3}\n"
+    );
+}
+
+#[test]
+fn locale_independent() {
+    for locale in &["C", "de_DE.UTF-8", "fr_FR.UTF-8"] {
+        let env = [
+            ("LC_ALL", *locale),
+            ("LC_NUMERIC", *locale),
+            ("LANG", *locale),
+        ];
+
+        assert_eq!(
+            rson_with_env(&["--format", "json"], &env, "[1.5, -0.25e3]"),
+            "[1.5,-250]\n"
+        );
+    }
+}
+
+#[test]
+fn format_json() {
+    assert_eq!(rson(&["--format", "json"], "[ 1,\n 2 ]\n"), "[1,2]\n");
+}
+
+#[test]
+fn json_by_default() {
+    let input = "{\"b\": [1, \"\\u00e9\\n\"], \"a\": {\"c\": null}}";
+    let output = rson(&[], input);
+    assert_eq!(parse(&output), parse(input), "{}", output);
+
+    let error = parse(&rson(&[], "[1,x]")).unwrap().into_value();
+    assert_eq!(error.pointer("/position"), Some(&Value::Number(3.0)));
+
+    assert_eq!(rson(&["--format", "debug"], "[1,x]"), "ParseError(3)\n");
+}
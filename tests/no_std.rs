@@ -0,0 +1,27 @@
+use std::process::Command;
+
+// The library without the `std` feature is `#![no_std]`, so checking that
+// build is enough to catch a `std` path creeping back into the core.
+#[test]
+fn builds_without_std() {
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "check",
+            "--lib",
+            "--no-default-features",
+            "--features",
+            "btree_map",
+        ])
+        .arg("--manifest-path")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        // A target directory of its own, as the one running this test is locked.
+        .arg("--target-dir")
+        .arg(concat!(env!("CARGO_TARGET_TMPDIR"), "/no_std"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}